@@ -0,0 +1,243 @@
+//! The interpreter as it was before instructions were cached, decoding every
+//! instruction as it runs, for the cached one to be measured against. Only
+//! what the benchmarks need is kept: the budget and loop detection are
+//! there, but never switched on, and inputs must all be queued up front.
+
+use aoc19::intcode::IntcodeError::{self, *};
+use aoc19::intcode::Opcode::{self, *};
+use aoc19::intcode::Parameter::{self, *};
+use aoc19::intcode::{Intcode, Memory};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashSet, VecDeque};
+use std::convert::{TryFrom, TryInto};
+use std::hash::{Hash, Hasher};
+
+pub struct Process {
+    pc: usize,
+    rb: i64,
+    memory: Memory<i64>,
+    inputs: VecDeque<i64>,
+    budget: Option<u64>,
+    seen: Option<HashSet<u64>>,
+}
+
+type Setter<'a> = Box<dyn FnMut(&Parameter<()>) -> Parameter<i64> + 'a>;
+
+enum Evaluation {
+    Input,
+    Output(i64),
+    Halt,
+    OutOfBudget,
+    Cycle,
+}
+use Evaluation::*;
+
+impl Process {
+    pub fn new(code: Intcode) -> Self {
+        Process {
+            pc: 0,
+            rb: 0,
+            memory: Memory::dense(code.into()),
+            inputs: VecDeque::new(),
+            budget: None,
+            seen: None,
+        }
+    }
+
+    pub fn with_inputs<I: IntoIterator<Item = i64>>(mut self, inputs: I) -> Self {
+        self.inputs.extend(inputs);
+        self
+    }
+
+    fn fault(&self, err: impl FnOnce(usize, i64) -> IntcodeError) -> IntcodeError {
+        err(self.pc, self.memory.get(self.pc))
+    }
+
+    fn to_address(&self, address: i64) -> Result<usize, IntcodeError> {
+        if address < 0 {
+            return Err(self.fault(|pc, instruction| NegativeAddress {
+                pc,
+                instruction,
+                address,
+            }));
+        }
+        let limit = self.memory.limit();
+        match address.try_into() {
+            Ok(x) if x < limit => Ok(x),
+            _ => Err(self.fault(|pc, instruction| OutOfBounds {
+                pc,
+                instruction,
+                address,
+                limit,
+            })),
+        }
+    }
+
+    fn address(&self, param: &Parameter<i64>) -> Result<usize, IntcodeError> {
+        match *param {
+            Imm(_) => Err(self.fault(|pc, instruction| WriteToImmediate { pc, instruction })),
+            Pos(p) => self.to_address(p),
+            Rel(p) => self.to_address(self.rb + p),
+        }
+    }
+
+    fn try_set(&mut self, param: &Parameter<i64>, value: i64) -> Result<(), IntcodeError> {
+        let x = self.address(param)?;
+        self.memory
+            .set(x, value)
+            .expect("addresses are checked by to_address");
+        Ok(())
+    }
+
+    fn get(&self, param: &Parameter<i64>) -> Result<i64, IntcodeError> {
+        match *param {
+            Imm(x) => Ok(x),
+            _ => Ok(self.memory.get(self.address(param)?)),
+        }
+    }
+
+    fn target(&self, param: &Parameter<i64>) -> Result<usize, IntcodeError> {
+        self.to_address(self.get(param)?)
+    }
+
+    fn jmp(&mut self, pos: usize) -> Option<Evaluation> {
+        if pos <= self.pc && self.revisits_state() {
+            return Some(Cycle);
+        }
+        self.pc = pos;
+        None
+    }
+
+    fn revisits_state(&mut self) -> bool {
+        if self.seen.is_none() {
+            return false;
+        }
+        let mut hasher = DefaultHasher::new();
+        (self.pc, self.rb, &self.memory).hash(&mut hasher);
+        let state = hasher.finish();
+        self.seen.as_mut().is_some_and(|seen| !seen.insert(state))
+    }
+
+    fn inc_setter(&self) -> Setter<'_> {
+        let mut i = 0;
+        Box::new(move |m| {
+            i += 1;
+            match m {
+                Pos(_) => Pos(self.memory.get(self.pc + i)),
+                Imm(_) => Imm(self.memory.get(self.pc + i)),
+                Rel(_) => Rel(self.memory.get(self.pc + i)),
+            }
+        })
+    }
+
+    fn current(&self) -> Result<Opcode<Parameter<i64>>, IntcodeError> {
+        let raw = self.memory.get(self.pc);
+        let word: i32 = raw
+            .try_into()
+            .map_err(|_| self.fault(|pc, instruction| UnknownOpcode { pc, instruction }))?;
+        let code = Opcode::<Parameter<()>>::try_from(word)
+            .map_err(|e| IntcodeError::decode(self.pc, raw, e))?;
+        let mut f = self.inc_setter();
+        Ok(match code {
+            Add(a, b, c) => Add(f(&a), f(&b), f(&c)),
+            Mul(a, b, c) => Mul(f(&a), f(&b), f(&c)),
+            Out(a) => Out(f(&a)),
+            Inp(a) => Inp(f(&a)),
+            Jnz(a, b) => Jnz(f(&a), f(&b)),
+            Jz(a, b) => Jz(f(&a), f(&b)),
+            Lt(a, b, c) => Lt(f(&a), f(&b), f(&c)),
+            Equ(a, b, c) => Equ(f(&a), f(&b), f(&c)),
+            Arb(a) => Arb(f(&a)),
+            Hlt => Hlt,
+        })
+    }
+
+    /// Runs until the program halts or runs out of input, returning its
+    /// outputs.
+    pub fn run(&mut self) -> Result<Vec<i64>, IntcodeError> {
+        let mut outputs = vec![];
+        loop {
+            match self.exec()? {
+                None => continue,
+                Some(Output(x)) => outputs.push(x),
+                Some(_) => return Ok(outputs),
+            }
+        }
+    }
+
+    fn exec(&mut self) -> Result<Option<Evaluation>, IntcodeError> {
+        if let Some(budget) = &mut self.budget {
+            if *budget == 0 {
+                return Ok(Some(OutOfBudget));
+            }
+            *budget -= 1;
+        }
+        let ev = match self.current()? {
+            Add(a, b, c) => {
+                self.try_set(&c, self.get(&a)? + self.get(&b)?)?;
+                self.pc += 4;
+                None
+            }
+            Mul(a, b, c) => {
+                let p1 = self.get(&a)?;
+                let p2 = self.get(&b)?;
+                self.try_set(&c, p1 * p2)?;
+                self.pc += 4;
+                None
+            }
+            Out(a) => {
+                let out = self.get(&a)?;
+                self.pc += 2;
+                Some(Output(out))
+            }
+            Inp(a) => {
+                let dest = self.address(&a)?;
+                match self.inputs.pop_front() {
+                    Some(x) => {
+                        self.try_set(&Pos(dest as i64), x)?;
+                        self.pc += 2;
+                        None
+                    }
+                    None => Some(Input),
+                }
+            }
+            Jnz(a, b) => {
+                if self.get(&a)? != 0 {
+                    let target = self.target(&b)?;
+                    self.jmp(target)
+                } else {
+                    self.pc += 3;
+                    None
+                }
+            }
+            Jz(a, b) => {
+                if self.get(&a)? == 0 {
+                    let target = self.target(&b)?;
+                    self.jmp(target)
+                } else {
+                    self.pc += 3;
+                    None
+                }
+            }
+            Lt(a, b, c) => {
+                let lt = self.get(&a)? < self.get(&b)?;
+                self.try_set(&c, if lt { 1 } else { 0 })?;
+                self.pc += 4;
+                None
+            }
+            Equ(a, b, c) => {
+                let eq = self.get(&a)? == self.get(&b)?;
+                self.try_set(&c, if eq { 1 } else { 0 })?;
+                self.pc += 4;
+                None
+            }
+            Arb(a) => {
+                self.rb += self.get(&a)?;
+                self.pc += 2;
+                None
+            }
+            Hlt => Some(Halt),
+        };
+        Ok(ev)
+    }
+}
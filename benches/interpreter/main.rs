@@ -0,0 +1,87 @@
+//! Compares the interpreter, with and without the instruction cache, with the
+//! one it replaced, which decoded every instruction as it ran. The cache only
+//! comes into play once a program jumps back, so programs without loops show
+//! what decoding and setting up a process cost:
+//!
+//! ```text
+//! cargo bench
+//! ```
+
+mod baseline;
+
+use aoc19::intcode::{asm, Intcode, Process};
+use std::time::{Duration, Instant};
+
+const COUNTDOWN: &str = "
+        INP -> [n]
+loop:   ADD [n], #-1 -> [n]
+        JNZ [n], #loop
+        OUT [n]
+        HLT
+n:      .data 0
+";
+
+/// Prints a copy of itself, looping once per word.
+const QUINE: &str = "109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99";
+
+/// Prints 999, 1000 or 1001 as its input is below, equal to or above 8,
+/// without any loop.
+const COMPARE_8: &str = "3,21,1008,21,8,20,1005,20,22,107,8,21,20,1006,20,31,\
+                         1106,0,36,98,0,0,1002,21,125,20,4,20,1105,1,46,104,\
+                         999,1105,1,46,1101,1000,1,20,4,20,1105,1,46,98,99";
+
+fn time<F: FnMut()>(mut f: F) -> Duration {
+    let start = Instant::now();
+    f();
+    start.elapsed()
+}
+
+fn bench(name: &str, code: &Intcode, input: i64, repeat: usize) {
+    let run = |cached: bool| {
+        for _ in 0..repeat {
+            let p = Process::new(code.clone()).with_inputs([input]);
+            let mut p = if cached { p } else { p.without_cache() };
+            p.outputs().for_each(drop);
+        }
+    };
+    // best of interleaved runs, to leave out warm up and the machine getting
+    // faster or slower in between
+    let mut best = [Duration::MAX; 3];
+    for _ in 0..50 {
+        let times = [
+            time(|| {
+                for _ in 0..repeat {
+                    baseline::Process::new(code.clone())
+                        .with_inputs([input])
+                        .run()
+                        .unwrap();
+                }
+            }),
+            time(|| run(false)),
+            time(|| run(true)),
+        ];
+        for (best, t) in best.iter_mut().zip(times.iter()) {
+            *best = (*best).min(*t);
+        }
+    }
+    let [baseline, uncached, cached] = best;
+    let speedup = |d: Duration| baseline.as_secs_f64() / d.as_secs_f64();
+    println!(
+        "{:<14} baseline {:>10.2?}  uncached {:>10.2?} ({:.2}x)  cached {:>10.2?} ({:.2}x)",
+        name,
+        baseline,
+        uncached,
+        speedup(uncached),
+        cached,
+        speedup(cached)
+    );
+}
+
+fn main() {
+    let countdown = asm::assemble(COUNTDOWN).unwrap();
+    bench("countdown", &countdown, 100_000, 1);
+    let quine: Intcode = QUINE.parse().unwrap();
+    bench("quine", &quine, 0, 1000);
+    let compare_8: Intcode = COMPARE_8.parse().unwrap();
+    bench("compare 8", &compare_8, 9, 3000);
+}
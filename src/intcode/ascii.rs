@@ -0,0 +1,136 @@
+//! Talking to programs that exchange text: lines go in as character codes
+//! followed by a newline, and output comes back as a `String` with any
+//! values outside the ASCII range (usually a final answer) set apart.
+
+use super::device::ascii_char;
+use super::ProcessStatus::*;
+use super::{Intcode, Process, ProcessStatus, Word};
+use std::io::{self, BufRead, Write};
+
+/// What the program printed between two inputs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AsciiOutput<W = i64> {
+    pub text: String,
+    /// Outputs that aren't ASCII characters, in order.
+    pub values: Vec<W>,
+}
+
+impl<W> Default for AsciiOutput<W> {
+    fn default() -> Self {
+        AsciiOutput {
+            text: String::new(),
+            values: vec![],
+        }
+    }
+}
+
+impl<W: Word> AsciiOutput<W> {
+    fn push(&mut self, value: W) {
+        match ascii_char(value) {
+            Some(c) => self.text.push(c),
+            None => self.values.push(value),
+        }
+    }
+}
+
+/// A process driven line by line.
+#[derive(Debug, Clone)]
+pub struct AsciiProcess<W = i64> {
+    process: Process<W>,
+}
+
+impl<W: Word> AsciiProcess<W> {
+    pub fn new(code: Intcode<W>) -> Self {
+        AsciiProcess {
+            process: Process::new(code),
+        }
+    }
+
+    pub fn with_process(process: Process<W>) -> Self {
+        AsciiProcess { process }
+    }
+
+    pub fn process(&self) -> &Process<W> {
+        &self.process
+    }
+
+    pub fn into_process(self) -> Process<W> {
+        self.process
+    }
+
+    pub fn status(&self) -> ProcessStatus<W> {
+        self.process.status()
+    }
+
+    /// Queues `line` followed by a newline as input.
+    pub fn send_line(&mut self, line: &str) {
+        for c in line.bytes().chain(Some(b'\n')) {
+            self.process.push_input(W::from(c as i32));
+        }
+    }
+
+    /// Runs until the program needs more input or stops, returning what it
+    /// printed meanwhile.
+    pub fn run(&mut self) -> AsciiOutput<W> {
+        self.process.run_until_blocked();
+        let mut output = AsciiOutput::default();
+        for x in self.process.take_output() {
+            output.push(x);
+        }
+        output
+    }
+
+    /// Sends `line` and runs until the program wants the next one.
+    pub fn interact(&mut self, line: &str) -> AsciiOutput<W> {
+        self.send_line(line);
+        self.run()
+    }
+
+    /// Connects the program to a terminal-like pair of streams: its text is
+    /// written to `output`, other values on lines of their own, and a line
+    /// of `input` is sent whenever it asks for one. Stops when the program
+    /// does or `input` ends.
+    pub fn interactive<R: BufRead, S: Write>(
+        &mut self,
+        mut input: R,
+        mut output: S,
+    ) -> io::Result<ProcessStatus<W>> {
+        loop {
+            let AsciiOutput { text, values } = self.run();
+            write!(output, "{}", text)?;
+            for x in values {
+                writeln!(output, "{}", x)?;
+            }
+            output.flush()?;
+            if !matches!(self.status(), Awaiting(_)) {
+                return Ok(self.status());
+            }
+            let mut line = String::new();
+            if input.read_line(&mut line)? == 0 {
+                return Ok(self.status());
+            }
+            self.send_line(line.trim_end_matches(&['\r', '\n'][..]));
+        }
+    }
+}
+
+#[test]
+pub fn ascii_test() {
+    let code = super::conformance::echo_until_zero();
+    let mut p = AsciiProcess::new(code.clone());
+    assert_eq!(p.run(), AsciiOutput::default());
+    let output = p.interact("hello");
+    assert_eq!(output.text, "hello\n");
+    assert!(output.values.is_empty());
+    p.process.feed(0).unwrap();
+    let output = p.run();
+    assert_eq!(output.text, "");
+    assert_eq!(output.values, vec![1000]);
+    assert_eq!(p.status(), Exit);
+
+    let mut p = AsciiProcess::new(code);
+    let mut output = vec![];
+    let status = p.interactive("one\ntwo\n".as_bytes(), &mut output).unwrap();
+    assert!(matches!(status, Awaiting(_)));
+    assert_eq!(output, b"one\ntwo\n");
+}
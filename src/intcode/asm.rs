@@ -0,0 +1,247 @@
+//! Assembler for the text format printed by the disassembler.
+//!
+//! ```text
+//! ; reads a number and prints it doubled
+//!         INP -> [x]
+//!         MUL [x], #2 -> [x]
+//!         OUT [x]
+//!         HLT
+//! x:      .data 0
+//! ```
+//!
+//! Operands are `#n` (immediate), `[n]` (position) or `[rb+n]` (relative),
+//! where `n` is a number or a label. A numeric prefix such as `0012:` is
+//! checked against the address of the line, so listings can be reassembled.
+
+use super::{Intcode, Word};
+use std::collections::BTreeMap;
+
+/// Mode digit and value of an operand.
+type Operand<W> = (i32, W);
+
+struct Statement<'a> {
+    line: usize,
+    address: usize,
+    op: &'a str,
+    args: Vec<String>,
+}
+
+/// Opcode and number of parameters of a mnemonic.
+fn opcode(mnemonic: &str) -> Option<(i32, usize)> {
+    let op = match mnemonic {
+        "ADD" => (1, 3),
+        "MUL" => (2, 3),
+        "INP" => (3, 1),
+        "OUT" => (4, 1),
+        "JNZ" => (5, 2),
+        "JZ" => (6, 2),
+        "LT" => (7, 3),
+        "EQU" => (8, 3),
+        "ARB" => (9, 1),
+        "HLT" => (99, 0),
+        _ => return None,
+    };
+    Some(op)
+}
+
+/// Which operand of an instruction it writes to, if any.
+fn write_operand(op: i32) -> Option<usize> {
+    match op {
+        1 | 2 | 7 | 8 => Some(2),
+        3 => Some(0),
+        _ => None,
+    }
+}
+
+fn is_label(s: &str) -> bool {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => {
+            chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        }
+        _ => false,
+    }
+}
+
+fn value<W: Word>(s: &str, labels: &BTreeMap<&str, usize>) -> Result<W, String> {
+    if let Ok(x) = s.parse() {
+        return Ok(x);
+    }
+    let address = labels
+        .get(s)
+        .ok_or_else(|| format!("unknown label {:?}", s))?;
+    W::try_from(*address).map_err(|_| format!("label {:?} does not fit in a word", s))
+}
+
+fn operand<W: Word>(s: &str, labels: &BTreeMap<&str, usize>) -> Result<Operand<W>, String> {
+    if let Some(x) = s.strip_prefix('#') {
+        return Ok((1, value(x.trim(), labels)?));
+    }
+    let inner = s
+        .strip_prefix('[')
+        .and_then(|x| x.strip_suffix(']'))
+        .ok_or_else(|| format!("invalid operand {:?}", s))?
+        .trim();
+    // `rb` only starts a relative operand on its own, so labels like `rbuf`
+    // are still positions
+    let relative = inner.strip_prefix("rb").filter(|rest| {
+        rest.is_empty() || rest.starts_with(|c: char| c.is_whitespace() || c == '+' || c == '-')
+    });
+    match relative {
+        Some("") => Ok((2, W::from(0))),
+        Some(offset) => {
+            let offset = offset.trim();
+            let x = offset.strip_prefix('+').unwrap_or(offset).trim();
+            Ok((2, value(x, labels)?))
+        }
+        None => Ok((0, value(inner, labels)?)),
+    }
+}
+
+fn split(source: &str) -> Result<(Vec<Statement<'_>>, BTreeMap<&str, usize>), String> {
+    let mut statements = vec![];
+    let mut labels = BTreeMap::new();
+    let mut address = 0;
+    for (n, text) in source.lines().enumerate() {
+        let line = n + 1;
+        let mut text = text.split(';').next().unwrap_or("").trim();
+        while let Some(i) = text.find(':') {
+            let name = text[..i].trim();
+            if is_label(name) {
+                if labels.insert(name, address).is_some() {
+                    Err(format!("line {}: duplicate label {:?}", line, name))?
+                }
+            } else if name.parse::<usize>().ok() != Some(address) {
+                Err(format!(
+                    "line {}: {:?} is not address {}",
+                    line, name, address
+                ))?
+            }
+            text = text[i + 1..].trim();
+        }
+        if text.is_empty() {
+            continue;
+        }
+        let (op, rest) = match text.find(char::is_whitespace) {
+            Some(i) => (&text[..i], text[i..].trim()),
+            None => (text, ""),
+        };
+        // the write operand may be introduced by `->` instead of a comma
+        let rest = rest.strip_prefix("->").unwrap_or(rest).replace("->", ",");
+        let args: Vec<String> = if rest.trim().is_empty() {
+            vec![]
+        } else {
+            rest.split(',').map(|a| a.trim().to_owned()).collect()
+        };
+        let size = if op == ".data" {
+            args.len()
+        } else {
+            let (_, params) =
+                opcode(op).ok_or_else(|| format!("line {}: unknown mnemonic {:?}", line, op))?;
+            if args.len() != params {
+                Err(format!(
+                    "line {}: {} takes {} operands, found {}",
+                    line,
+                    op,
+                    params,
+                    args.len()
+                ))?
+            }
+            params + 1
+        };
+        statements.push(Statement {
+            line,
+            address,
+            op,
+            args,
+        });
+        address += size;
+    }
+    Ok((statements, labels))
+}
+
+/// Assembles `source` into a program.
+pub fn assemble<W: Word>(source: &str) -> Result<Intcode<W>, String> {
+    let (statements, labels) = split(source)?;
+    let mut code = vec![];
+    for s in statements {
+        let at = |e: String| format!("line {}: {}", s.line, e);
+        debug_assert_eq!(code.len(), s.address);
+        if s.op == ".data" {
+            for a in &s.args {
+                code.push(value(a, &labels).map_err(at)?);
+            }
+            continue;
+        }
+        let (op, _) = opcode(s.op).unwrap();
+        let operands: Vec<Operand<W>> = s
+            .args
+            .iter()
+            .map(|a| operand(a, &labels))
+            .collect::<Result<_, _>>()
+            .map_err(at)?;
+        if let Some(i) = write_operand(op) {
+            if operands[i].0 == 1 {
+                Err(at(format!("can't write to immediate {:?}", s.args[i])))?
+            }
+        }
+        let modes = operands
+            .iter()
+            .rev()
+            .fold(0, |acc, (mode, _)| acc * 10 + mode);
+        code.push(W::from(modes * 100 + op));
+        code.extend(operands.iter().map(|(_, x)| *x));
+    }
+    Ok(Intcode(code))
+}
+
+#[test]
+pub fn assemble_test() {
+    let source = "
+        ; reads a number and prints it doubled until it is zero
+        loop:   INP -> [rb+x]
+                JZ [x], #end
+                MUL [x], #2 -> [x]
+                OUT [x]
+                JNZ #1, #loop
+        end:    HLT
+        x:      .data 0
+    ";
+    let code: Intcode = assemble(source).unwrap();
+    assert_eq!(
+        code,
+        Intcode(vec![
+            203, 15, 1006, 15, 14, 1002, 15, 2, 15, 4, 15, 1105, 1, 0, 99, 0
+        ])
+    );
+    assert!(assemble::<i64>("ADD #1, #2").is_err());
+    assert!(assemble::<i64>("JNZ #1, #nowhere").is_err());
+    assert!(assemble::<i64>("ADD #1, #2 -> #3").is_err());
+    assert!(assemble::<i64>("INP -> #3").is_err());
+
+    let code: Intcode = assemble("OUT [rbuf]\nOUT [rb-1]\nHLT\nrbuf: .data 7").unwrap();
+    assert_eq!(code, Intcode(vec![4, 5, 204, -1, 99, 7]));
+    let source = "OUT [rbuf]\nHLT\nuf: .data 1\nrbuf: .data 7";
+    assert_eq!(assemble(source), Ok(Intcode(vec![4, 4, 99, 1, 7])));
+}
+
+#[test]
+pub fn round_trip_test() {
+    // examples from the puzzles, mixing code and data
+    let examples = [
+        "1,9,10,3,2,3,11,0,99,30,40,50",
+        "3,3,1105,-1,9,1101,0,0,12,4,12,99,1",
+        "3,21,1008,21,8,20,1005,20,22,107,8,21,20,1006,20,31,\
+         1106,0,36,98,0,0,1002,21,125,20,4,20,1105,1,46,104,\
+         999,1105,1,46,1101,1000,1,20,4,20,1105,1,46,98,99",
+        "3,26,1001,26,-4,26,3,27,1002,27,2,27,1,27,26,27,4,27,\
+         1001,28,-1,28,1005,28,6,99,0,0,5",
+        "109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99",
+        "1102,34915192,34915192,7,4,7,99,0",
+    ];
+    for example in examples.iter() {
+        let code: Intcode = example.parse().unwrap();
+        let listing = super::disasm::listing(&code);
+        assert_eq!(assemble(&listing), Ok(code), "{}", example);
+    }
+}
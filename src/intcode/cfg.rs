@@ -0,0 +1,243 @@
+//! Control-flow graphs of programs, exported as Graphviz DOT:
+//!
+//! ```text
+//! cargo run -- cfg program.txt | dot -Tsvg > program.svg
+//! ```
+//!
+//! The graph only covers code reachable from address 0 through jumps with
+//! immediate targets. Jumps to an address read from memory are flagged as
+//! computed, and code that modifies itself is analysed as it is on disk.
+
+use super::disasm::{decode, Item, Line};
+use super::Opcode::*;
+use super::Parameter::*;
+use super::{Intcode, Opcode, Parameter, Word};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+/// Where control goes after the last instruction of a block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Exit {
+    /// Falls through into the block starting at this address.
+    Next(usize),
+    Jump(usize),
+    Branch {
+        taken: usize,
+        not_taken: usize,
+    },
+    /// Jumps to an address only known at run time, or goes on to
+    /// `not_taken` when the jump is conditional.
+    Computed {
+        not_taken: Option<usize>,
+    },
+    Halt,
+    /// Runs into a word that isn't an instruction or off the end of the
+    /// program.
+    Invalid,
+}
+use Exit::*;
+
+impl Exit {
+    /// Blocks control may statically go to.
+    pub fn successors(&self) -> Vec<usize> {
+        match *self {
+            Next(x) | Jump(x) => vec![x],
+            Branch { taken, not_taken } => vec![taken, not_taken],
+            Computed { not_taken } => not_taken.into_iter().collect(),
+            Halt | Invalid => vec![],
+        }
+    }
+}
+
+/// Where control goes after the instruction at `address`.
+fn flow<W: Word>(address: usize, op: &Opcode<Parameter<W>>) -> Exit {
+    let next = address + op.size();
+    let (cond, target, jump_if) = match op {
+        Jnz(cond, target) => (cond, target, true),
+        Jz(cond, target) => (cond, target, false),
+        Hlt => return Halt,
+        _ => return Next(next),
+    };
+    let taken = match *cond {
+        Imm(c) if (c != W::from(0)) == jump_if => Some(true),
+        Imm(_) => Some(false),
+        _ => None,
+    };
+    // a negative immediate target faults at run time, so it is no better
+    // known than a computed one
+    let target = match *target {
+        Imm(t) => t.try_into().ok(),
+        _ => None,
+    };
+    match (taken, target) {
+        (Some(false), _) => Next(next),
+        (Some(true), Some(t)) => Jump(t),
+        (None, Some(t)) => Branch {
+            taken: t,
+            not_taken: next,
+        },
+        (Some(true), None) => Computed { not_taken: None },
+        (None, None) => Computed {
+            not_taken: Some(next),
+        },
+    }
+}
+
+/// A run of instructions only entered at the top and left at the bottom.
+#[derive(Debug, PartialEq)]
+pub struct Block<W> {
+    pub start: usize,
+    pub lines: Vec<Line<W>>,
+    pub exit: Exit,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Cfg<W = i64> {
+    /// Blocks by start address.
+    pub blocks: BTreeMap<usize, Block<W>>,
+}
+
+impl<W: Word> Cfg<W> {
+    pub fn new(code: &Intcode<W>) -> Self {
+        let cells = &code.0;
+        // find reachable instructions and where blocks start
+        let mut leaders = BTreeSet::new();
+        leaders.insert(0);
+        let mut seen = BTreeSet::new();
+        let mut todo = vec![0];
+        while let Some(address) = todo.pop() {
+            if !seen.insert(address) {
+                continue;
+            }
+            let exit = match decode(cells, address) {
+                Some(op) => flow(address, &op),
+                None => Invalid,
+            };
+            if !matches!(exit, Next(_)) {
+                leaders.extend(exit.successors());
+            }
+            todo.extend(exit.successors());
+        }
+
+        let blocks = leaders
+            .iter()
+            .map(|&start| {
+                let mut lines = vec![];
+                let mut address = start;
+                let exit = loop {
+                    let op = match decode(cells, address) {
+                        Some(op) => op,
+                        None => break Invalid,
+                    };
+                    let item = Item::Instruction(op);
+                    lines.push(Line { address, item });
+                    match flow(address, &op) {
+                        Next(x) if !leaders.contains(&x) => address = x,
+                        exit => break exit,
+                    }
+                };
+                (start, Block { start, lines, exit })
+            })
+            .collect();
+        Cfg { blocks }
+    }
+
+    /// Addresses of the jumps whose targets aren't known statically.
+    pub fn computed_jumps(&self) -> Vec<usize> {
+        self.blocks
+            .values()
+            .filter(|b| matches!(b.exit, Computed { .. }))
+            .filter_map(|b| b.lines.last().map(|l| l.address))
+            .collect()
+    }
+
+    /// The graph in Graphviz DOT format. Computed jumps point at a `?` node
+    /// and blocks running into data are drawn in red.
+    pub fn to_dot(&self) -> String {
+        let mut dot =
+            String::from("digraph cfg {\n    node [shape=box, fontname=\"monospace\"];\n");
+        for block in self.blocks.values() {
+            let label: String = block
+                .lines
+                .iter()
+                .map(|l| format!("{}\\l", l.to_string().replace('"', "\\\"")))
+                .collect();
+            let label = if label.is_empty() {
+                format!("{:04}: ???\\l", block.start)
+            } else {
+                label
+            };
+            let color = match block.exit {
+                Invalid => ", color=red",
+                _ => "",
+            };
+            writeln!(dot, "    b{} [label=\"{}\"{}];", block.start, label, color).unwrap();
+            let from = block.start;
+            match block.exit {
+                Next(x) | Jump(x) => writeln!(dot, "    b{} -> b{};", from, x).unwrap(),
+                Branch { taken, not_taken } => {
+                    writeln!(dot, "    b{} -> b{} [label=\"taken\"];", from, taken).unwrap();
+                    writeln!(
+                        dot,
+                        "    b{} -> b{} [label=\"not taken\"];",
+                        from, not_taken
+                    )
+                    .unwrap();
+                }
+                Computed { not_taken } => {
+                    writeln!(dot, "    c{} [label=\"?\", shape=circle];", from).unwrap();
+                    writeln!(dot, "    b{} -> c{} [style=dashed];", from, from).unwrap();
+                    if let Some(x) = not_taken {
+                        writeln!(dot, "    b{} -> b{} [label=\"not taken\"];", from, x).unwrap();
+                    }
+                }
+                Halt | Invalid => {}
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+#[test]
+pub fn cfg_test() {
+    // reads n, then counts it down to zero
+    let code: Intcode = "3,13,1006,13,12,1001,13,-1,13,1105,1,2,99,0"
+        .parse()
+        .unwrap();
+    let cfg = Cfg::new(&code);
+    let exits: Vec<(usize, Exit)> = cfg.blocks.values().map(|b| (b.start, b.exit)).collect();
+    assert_eq!(
+        exits,
+        vec![
+            (0, Next(2)),
+            (
+                2,
+                Branch {
+                    taken: 12,
+                    not_taken: 5
+                }
+            ),
+            (5, Jump(2)),
+            (12, Halt),
+        ]
+    );
+    assert!(cfg.computed_jumps().is_empty());
+    assert_eq!(cfg.blocks[&5].lines.len(), 2);
+
+    let code: Intcode = "1106,0,3,5,6,3,99".parse().unwrap();
+    let cfg = Cfg::new(&code);
+    assert_eq!(cfg.blocks[&3].exit, Computed { not_taken: Some(6) });
+    assert_eq!(cfg.computed_jumps(), vec![3]);
+    assert_eq!(cfg.blocks[&6].exit, Halt);
+    let dot = cfg.to_dot();
+    assert!(dot.contains("    b0 -> b3;\n"));
+    assert!(dot.contains("    b3 -> c3 [style=dashed];\n"));
+    assert!(dot.contains("    b6 [label=\"0006: HLT\\l\"];\n"));
+
+    // unused mode digits are ignored, as the interpreter does
+    let code: Intcode = "10104,5,99".parse().unwrap();
+    let cfg = Cfg::new(&code);
+    assert_eq!(cfg.blocks[&0].exit, Halt);
+    assert_eq!(cfg.blocks[&0].lines.len(), 2);
+}
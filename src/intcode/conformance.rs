@@ -0,0 +1,133 @@
+//! Example programs from the puzzle descriptions, covering every opcode and
+//! parameter mode without needing anyone's puzzle input, and the sample
+//! programs the other modules' tests share.
+
+use super::{Intcode, Process, ProcessStatus::*};
+
+fn code(source: &str) -> Intcode {
+    source.parse().unwrap()
+}
+
+/// Prints the running total of its inputs, forever.
+pub(super) fn running_total() -> Intcode {
+    code("3,11,1,11,12,12,4,12,1105,1,0,0,0")
+}
+
+/// Echoes its input until it reads a zero, then prints 1000.
+pub(super) fn echo_until_zero() -> Intcode {
+    code("3,13,1006,13,10,4,13,1105,1,0,104,1000,99,0")
+}
+
+/// Runs `source` to completion with `inputs` queued, returning its outputs.
+fn outputs(source: &str, inputs: &[i64]) -> Vec<i64> {
+    let mut p = Process::new(code(source)).with_inputs(inputs.iter().copied());
+    let outputs = p.outputs().collect();
+    assert_eq!(p.status(), Exit, "{} with {:?}", source, inputs);
+    outputs
+}
+
+/// Runs `source` to completion, returning its memory.
+fn memory(source: &str) -> Vec<i64> {
+    let mut p = Process::new(code(source));
+    assert_eq!(p.resume(), Exit, "{}", source);
+    p.memory().cells().map(|(_, x)| x).collect()
+}
+
+#[test]
+pub fn add_mul_test() {
+    let cases = [
+        (
+            "1,9,10,3,2,3,11,0,99,30,40,50",
+            "3500,9,10,70,2,3,11,0,99,30,40,50",
+        ),
+        ("1,0,0,0,99", "2,0,0,0,99"),
+        ("2,3,0,3,99", "2,3,0,6,99"),
+        ("2,4,4,5,99,0", "2,4,4,5,99,9801"),
+        ("1,1,1,4,99,5,6,0,99", "30,1,1,4,2,5,6,0,99"),
+    ];
+    for (source, expected) in cases.iter() {
+        assert_eq!(memory(source), code(expected).0, "{}", source);
+    }
+}
+
+#[test]
+pub fn modes_test() {
+    assert_eq!(memory("1002,4,3,4,33"), vec![1002, 4, 3, 4, 99]);
+    assert_eq!(memory("1101,100,-1,4,0"), vec![1101, 100, -1, 4, 99]);
+}
+
+#[test]
+pub fn io_test() {
+    assert_eq!(outputs("3,0,4,0,99", &[42]), vec![42]);
+    assert_eq!(outputs("104,-7,99", &[]), vec![-7]);
+}
+
+#[test]
+pub fn comparison_test() {
+    let equals_8 = ["3,9,8,9,10,9,4,9,99,-1,8", "3,3,1108,-1,8,3,4,3,99"];
+    let less_than_8 = ["3,9,7,9,10,9,4,9,99,-1,8", "3,3,1107,-1,8,3,4,3,99"];
+    for input in 5..=11 {
+        for source in equals_8.iter() {
+            let expected = if input == 8 { 1 } else { 0 };
+            assert_eq!(outputs(source, &[input]), vec![expected], "{}", source);
+        }
+        for source in less_than_8.iter() {
+            let expected = if input < 8 { 1 } else { 0 };
+            assert_eq!(outputs(source, &[input]), vec![expected], "{}", source);
+        }
+    }
+}
+
+#[test]
+pub fn jump_test() {
+    let nonzero = [
+        "3,12,6,12,15,1,13,14,13,4,13,99,-1,0,1,9",
+        "3,3,1105,-1,9,1101,0,0,12,4,12,99,1",
+    ];
+    for source in nonzero.iter() {
+        assert_eq!(outputs(source, &[0]), vec![0], "{}", source);
+        assert_eq!(outputs(source, &[-3]), vec![1], "{}", source);
+        assert_eq!(outputs(source, &[5]), vec![1], "{}", source);
+    }
+
+    // 999 below 8, 1000 at 8, 1001 above
+    let compare_8 = "3,21,1008,21,8,20,1005,20,22,107,8,21,20,1006,20,31,\
+                     1106,0,36,98,0,0,1002,21,125,20,4,20,1105,1,46,104,\
+                     999,1105,1,46,1101,1000,1,20,4,20,1105,1,46,98,99";
+    assert_eq!(outputs(compare_8, &[7]), vec![999]);
+    assert_eq!(outputs(compare_8, &[8]), vec![1000]);
+    assert_eq!(outputs(compare_8, &[9]), vec![1001]);
+}
+
+#[test]
+pub fn relative_test() {
+    let quine = "109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99";
+    assert_eq!(outputs(quine, &[]), code(quine).0);
+    // relative writes
+    assert_eq!(outputs("109,10,203,-3,204,-3,99", &[5]), vec![5]);
+    assert_eq!(outputs("109,-1,4,1,99", &[]), vec![-1]);
+    assert_eq!(outputs("109,-1,104,1,99", &[]), vec![1]);
+    assert_eq!(outputs("109,-1,204,1,99", &[]), vec![109]);
+    assert_eq!(outputs("109,1,9,2,204,-6,99", &[]), vec![204]);
+    assert_eq!(outputs("109,1,109,9,204,-6,99", &[]), vec![204]);
+    assert_eq!(outputs("109,1,209,-1,204,-106,99", &[]), vec![204]);
+    assert_eq!(outputs("109,1,3,3,204,2,99", &[7]), vec![7]);
+    assert_eq!(outputs("109,1,203,2,204,2,99", &[7]), vec![7]);
+}
+
+#[test]
+pub fn large_numbers_test() {
+    let product = outputs("1102,34915192,34915192,7,4,7,99,0", &[]);
+    assert_eq!(product, vec![1219070632396864]);
+    assert_eq!(product[0].to_string().len(), 16);
+    assert_eq!(
+        outputs("104,1125899906842624,99", &[]),
+        vec![1125899906842624]
+    );
+}
+
+#[test]
+pub fn growing_memory_test() {
+    // reads and writes well past the end of the program
+    assert_eq!(outputs("1101,3,4,1000,4,1000,4,999,99", &[]), vec![7, 0]);
+}
@@ -0,0 +1,258 @@
+use super::disasm::{Item, Line};
+use super::{Process, ProcessStatus, Word};
+use std::collections::BTreeSet;
+use std::io::{self, BufRead, Write};
+
+/// Why the debugger handed control back.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event<W> {
+    /// A single step finished without anything noteworthy happening.
+    Stepped,
+    Breakpoint(usize),
+    Watchpoint {
+        address: usize,
+        old: W,
+        new: W,
+    },
+    /// The process yielded: output, input request, halt or fault.
+    Yield(ProcessStatus<W>),
+}
+use Event::*;
+
+/// Runs a process instruction by instruction, stopping at breakpoints (by
+/// address) and watchpoints (on memory cells).
+#[derive(Debug, Clone)]
+pub struct Debugger<W = i64> {
+    process: Process<W>,
+    breakpoints: BTreeSet<usize>,
+    watchpoints: BTreeSet<usize>,
+    /// The breakpoint `cont` last stopped at, so that continuing from it
+    /// doesn't stop there again straight away.
+    stopped_at: Option<usize>,
+}
+
+impl<W: Word> Debugger<W> {
+    pub fn new(process: Process<W>) -> Self {
+        Debugger {
+            process,
+            breakpoints: BTreeSet::new(),
+            watchpoints: BTreeSet::new(),
+            stopped_at: None,
+        }
+    }
+
+    pub fn process(&self) -> &Process<W> {
+        &self.process
+    }
+
+    pub fn break_at(&mut self, address: usize) {
+        self.breakpoints.insert(address);
+    }
+
+    pub fn watch(&mut self, address: usize) {
+        self.watchpoints.insert(address);
+    }
+
+    /// Removes the breakpoint and watchpoint at `address`, if any.
+    pub fn clear(&mut self, address: usize) -> bool {
+        let b = self.breakpoints.remove(&address);
+        let w = self.watchpoints.remove(&address);
+        b || w
+    }
+
+    pub fn feed(&mut self, input: W) -> Result<ProcessStatus<W>, super::IntcodeError<W>> {
+        self.stopped_at = None;
+        self.process.feed(input)
+    }
+
+    pub fn step(&mut self) -> Event<W> {
+        self.stopped_at = None;
+        let memory = self.process.memory();
+        let before: Vec<(usize, W)> = self
+            .watchpoints
+            .iter()
+            .map(|&a| (a, memory.get(a)))
+            .collect();
+
+        let status = self.process.step();
+
+        let memory = self.process.memory();
+        let changed = before
+            .into_iter()
+            .map(|(address, old)| (address, old, memory.get(address)))
+            .find(|(_, old, new)| old != new);
+        match (changed, status) {
+            (Some((address, old, new)), _) => Watchpoint { address, old, new },
+            (None, ProcessStatus::Paused) => Stepped,
+            (None, s) => Yield(s),
+        }
+    }
+
+    /// Steps until a breakpoint, a watchpoint or the process yields. The
+    /// breakpoint at the pc is checked before every step, wherever the pc got
+    /// there from, except the one `cont` is continuing from.
+    pub fn cont(&mut self) -> Event<W> {
+        loop {
+            let pc = self.process.pc();
+            if self.breakpoints.contains(&pc) && self.stopped_at != Some(pc) {
+                self.stopped_at = Some(pc);
+                return Breakpoint(pc);
+            }
+            match self.step() {
+                Stepped => continue,
+                e => return e,
+            }
+        }
+    }
+
+    /// Describes the current state and the instruction about to execute.
+    pub fn location(&self) -> String {
+        let p = &self.process;
+        let instruction = match p.current() {
+            Ok(op) => Line {
+                address: p.pc(),
+                item: Item::Instruction(op),
+            }
+            .to_string(),
+            Err(e) => e.to_string(),
+        };
+        format!(
+            "{}    ; rb={} {:?}",
+            instruction,
+            p.relative_base(),
+            p.status()
+        )
+    }
+
+    fn report(&self, event: Event<W>, out: &mut impl Write) -> io::Result<()> {
+        match event {
+            Stepped => {}
+            Breakpoint(a) => writeln!(out, "breakpoint at {:04}", a)?,
+            Watchpoint { address, old, new } => {
+                writeln!(out, "watchpoint {:04}: {} -> {}", address, old, new)?
+            }
+            Yield(ProcessStatus::Outputting(x)) => writeln!(out, "output: {}", x)?,
+            Yield(ProcessStatus::Awaiting(_)) => writeln!(out, "awaiting input, use `i <value>`")?,
+            Yield(ProcessStatus::Exit) => writeln!(out, "halted")?,
+            Yield(ProcessStatus::Faulted(e)) => writeln!(out, "fault: {}", e)?,
+            Yield(ProcessStatus::Exhausted) => writeln!(out, "instruction budget exhausted")?,
+            Yield(ProcessStatus::Looping(a)) => writeln!(out, "stuck in a loop at {:04}", a)?,
+            Yield(ProcessStatus::Paused) => {}
+        }
+        writeln!(out, "{}", self.location())
+    }
+
+    /// Runs an interactive session reading commands from `input`.
+    pub fn repl(&mut self, input: impl BufRead, mut out: impl Write) -> io::Result<()> {
+        writeln!(out, "{}", self.location())?;
+        for line in input.lines() {
+            let line = line?;
+            let words: Vec<&str> = line.split_whitespace().collect();
+            let address = |i: usize| words.get(i).and_then(|w| w.parse::<usize>().ok());
+            match words.as_slice() {
+                [] => continue,
+                ["s"] | ["step"] => {
+                    let e = self.step();
+                    self.report(e, &mut out)?
+                }
+                ["c"] | ["continue"] => {
+                    let e = self.cont();
+                    self.report(e, &mut out)?
+                }
+                ["b", _] | ["break", _] => match address(1) {
+                    Some(a) => self.break_at(a),
+                    None => writeln!(out, "invalid address")?,
+                },
+                ["w", _] | ["watch", _] => match address(1) {
+                    Some(a) => self.watch(a),
+                    None => writeln!(out, "invalid address")?,
+                },
+                ["d", _] | ["delete", _] => match address(1) {
+                    Some(a) if self.clear(a) => {}
+                    _ => writeln!(out, "nothing set there")?,
+                },
+                ["p"] | ["print"] => writeln!(out, "{}", self.location())?,
+                ["x", _] | ["x", _, _] => {
+                    let from = address(1).unwrap_or(0);
+                    let n = address(2).unwrap_or(1);
+                    let memory = self.process.memory();
+                    for a in from..from + n {
+                        writeln!(out, "{:04}: {}", a, memory.get(a))?;
+                    }
+                }
+                ["i", x] | ["input", x] => match x.parse() {
+                    Ok(x) => match self.feed(x) {
+                        Ok(_) => writeln!(out, "{}", self.location())?,
+                        Err(e) => writeln!(out, "{}", e)?,
+                    },
+                    Err(_) => writeln!(out, "invalid value")?,
+                },
+                ["q"] | ["quit"] => break,
+                _ => writeln!(
+                    out,
+                    "commands: s(tep), c(ontinue), b(reak) <addr>, w(atch) <addr>, \
+                     d(elete) <addr>, p(rint), x <addr> [n], i(nput) <value>, q(uit)"
+                )?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[test]
+pub fn debugger_test() {
+    use super::Intcode;
+    // counts [10] down from 3 and prints zero
+    let code: Intcode = "1001,10,-1,10,1005,10,0,4,10,99,3".parse().unwrap();
+    let mut d = Debugger::new(Process::new(code));
+    assert_eq!(d.step(), Stepped);
+    d.break_at(7);
+    d.watch(10);
+    assert_eq!(
+        d.cont(),
+        Watchpoint {
+            address: 10,
+            old: 2,
+            new: 1
+        }
+    );
+    d.clear(10);
+    assert_eq!(d.cont(), Breakpoint(7));
+    assert_eq!(d.cont(), Yield(ProcessStatus::Outputting(0)));
+    assert_eq!(d.cont(), Yield(ProcessStatus::Exit));
+}
+
+#[test]
+pub fn breakpoint_after_io_test() {
+    use super::{Intcode, Parameter};
+    // prints 1, then adds into [9] at 2
+    let code: Intcode = "104,1,1101,0,0,9,99,0,0,0".parse().unwrap();
+    let mut d = Debugger::new(Process::new(code));
+    d.break_at(2);
+    assert_eq!(d.cont(), Yield(ProcessStatus::Outputting(1)));
+    assert_eq!(d.cont(), Breakpoint(2));
+    assert_eq!(d.cont(), Yield(ProcessStatus::Exit));
+
+    // reads into [9], then adds into it at 2
+    let code: Intcode = "3,9,1101,0,0,9,99,0,0,0".parse().unwrap();
+    let mut d = Debugger::new(Process::new(code));
+    d.break_at(2);
+    assert_eq!(d.cont(), Yield(ProcessStatus::Awaiting(Parameter::Pos(9))));
+    d.feed(5).unwrap();
+    assert_eq!(d.cont(), Breakpoint(2));
+    assert_eq!(d.cont(), Yield(ProcessStatus::Exit));
+}
+
+#[test]
+pub fn repl_test() {
+    use super::Intcode;
+    let code: Intcode = "3,5,4,5,99,0".parse().unwrap();
+    let mut d = Debugger::new(Process::new(code));
+    let mut out = vec![];
+    d.repl("c\ni 7\nx 5\nc\nc\n".as_bytes(), &mut out).unwrap();
+    let out = String::from_utf8(out).unwrap();
+    assert!(out.contains("awaiting input"));
+    assert!(out.contains("0005: 7\n"));
+    assert!(out.contains("output: 7\n"));
+    assert!(out.contains("halted\n"));
+}
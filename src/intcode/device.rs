@@ -0,0 +1,285 @@
+//! Input and output devices a process can be attached to, so the same
+//! program can be driven by a test, a console or another thread without
+//! writing the resume/feed loop again.
+//!
+//! Closures, `VecDeque`s, `Vec`s and channels are devices as they are.
+//! `Numbers` and `Ascii` read from a `BufRead` and write to a `Write`, one
+//! number per line or one character per value.
+
+use super::ProcessStatus::*;
+use super::{Process, ProcessStatus, Word};
+use std::collections::VecDeque;
+use std::io::{self, BufRead, Write};
+use std::sync::mpsc::{Receiver, Sender};
+
+/// Where a process reads its input from.
+pub trait InputDevice<W> {
+    /// The next input, or `None` if there is none (yet).
+    fn read(&mut self) -> io::Result<Option<W>>;
+}
+
+/// Where a process writes its output to.
+pub trait OutputDevice<W> {
+    fn write(&mut self, value: W) -> io::Result<()>;
+}
+
+impl<W, F: FnMut() -> Option<W>> InputDevice<W> for F {
+    fn read(&mut self) -> io::Result<Option<W>> {
+        Ok(self())
+    }
+}
+
+impl<W, F: FnMut(W)> OutputDevice<W> for F {
+    fn write(&mut self, value: W) -> io::Result<()> {
+        self(value);
+        Ok(())
+    }
+}
+
+impl<W> InputDevice<W> for VecDeque<W> {
+    fn read(&mut self) -> io::Result<Option<W>> {
+        Ok(self.pop_front())
+    }
+}
+
+impl<W> OutputDevice<W> for Vec<W> {
+    fn write(&mut self, value: W) -> io::Result<()> {
+        self.push(value);
+        Ok(())
+    }
+}
+
+/// Blocks until a value arrives, or gives `None` once the sender is gone.
+impl<W> InputDevice<W> for Receiver<W> {
+    fn read(&mut self) -> io::Result<Option<W>> {
+        Ok(self.recv().ok())
+    }
+}
+
+/// Fails with `BrokenPipe` once the receiver is gone.
+impl<W> OutputDevice<W> for Sender<W> {
+    fn write(&mut self, value: W) -> io::Result<()> {
+        self.send(value)
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "receiver is gone"))
+    }
+}
+
+/// Numbers in decimal, one per line.
+#[derive(Debug)]
+pub struct Numbers<T>(pub T);
+
+impl Numbers<io::StdinLock<'static>> {
+    pub fn stdin() -> Self {
+        Numbers(io::stdin().lock())
+    }
+}
+
+impl Numbers<io::Stdout> {
+    pub fn stdout() -> Self {
+        Numbers(io::stdout())
+    }
+}
+
+/// Skips blank lines and gives `None` at the end of the input. A line that
+/// isn't a number fails with `InvalidData`, after which reading goes on with
+/// the next line, so a typo at the console needn't end the session.
+impl<W: Word, T: BufRead> InputDevice<W> for Numbers<T> {
+    fn read(&mut self) -> io::Result<Option<W>> {
+        let mut line = String::new();
+        loop {
+            line.clear();
+            if self.0.read_line(&mut line)? == 0 {
+                return Ok(None);
+            }
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            return match line.parse() {
+                Ok(x) => Ok(Some(x)),
+                Err(_) => Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("not a number: {:?}", line),
+                )),
+            };
+        }
+    }
+}
+
+impl<W: Word, T: Write> OutputDevice<W> for Numbers<T> {
+    fn write(&mut self, value: W) -> io::Result<()> {
+        writeln!(self.0, "{}", value)
+    }
+}
+
+/// Text, one character code per value. Values outside the ASCII range are
+/// written as numbers on a line of their own.
+#[derive(Debug)]
+pub struct Ascii<T> {
+    inner: T,
+    pending: VecDeque<u8>,
+}
+
+impl<T> Ascii<T> {
+    pub fn new(inner: T) -> Self {
+        Ascii {
+            inner,
+            pending: VecDeque::new(),
+        }
+    }
+
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl Ascii<io::StdinLock<'static>> {
+    pub fn stdin() -> Self {
+        Ascii::new(io::stdin().lock())
+    }
+}
+
+impl Ascii<io::Stdout> {
+    pub fn stdout() -> Self {
+        Ascii::new(io::stdout())
+    }
+}
+
+/// Reads a whole line at a time, handing it out with its newline.
+impl<W: Word, T: BufRead> InputDevice<W> for Ascii<T> {
+    fn read(&mut self) -> io::Result<Option<W>> {
+        if self.pending.is_empty() {
+            let mut line = String::new();
+            if self.inner.read_line(&mut line)? == 0 {
+                return Ok(None);
+            }
+            let line = line.trim_end_matches(&['\r', '\n'][..]);
+            self.pending.extend(line.bytes());
+            self.pending.push_back(b'\n');
+        }
+        Ok(self.pending.pop_front().map(|c| W::from(c as i32)))
+    }
+}
+
+/// The character `value` codes for, if it is in the ASCII range.
+pub(crate) fn ascii_char<W: Word>(value: W) -> Option<char> {
+    match value.try_into() {
+        Ok(c @ 0..=127) => Some(c as u8 as char),
+        _ => None,
+    }
+}
+
+impl<W: Word, T: Write> OutputDevice<W> for Ascii<T> {
+    fn write(&mut self, value: W) -> io::Result<()> {
+        match ascii_char(value) {
+            Some(c) => {
+                write!(self.inner, "{}", c)?;
+                if c == '\n' {
+                    self.inner.flush()?;
+                }
+                Ok(())
+            }
+            None => writeln!(self.inner, "{}", value),
+        }
+    }
+}
+
+impl<W: Word> Process<W> {
+    /// Runs the program, reading input from `input` and writing output to
+    /// `output`, until it halts or stops for any other reason. If `input`
+    /// runs dry the process is left `Awaiting`. Fails if reading `input`
+    /// does, with the process left `Awaiting`, or if writing to `output`
+    /// does, with the process left `Outputting` the value it couldn't write.
+    pub fn run_with<I, O>(&mut self, input: &mut I, output: &mut O) -> io::Result<ProcessStatus<W>>
+    where
+        I: InputDevice<W> + ?Sized,
+        O: OutputDevice<W> + ?Sized,
+    {
+        loop {
+            match self.resume() {
+                Outputting(x) => output.write(x)?,
+                Awaiting(_) => match input.read()? {
+                    Some(x) => {
+                        self.feed(x).expect("process is awaiting input");
+                    }
+                    None => return Ok(self.status()),
+                },
+                Paused => (),
+                s => return Ok(s),
+            }
+        }
+    }
+}
+
+#[test]
+pub fn device_test() {
+    use std::sync::mpsc::channel;
+
+    let code = super::conformance::running_total();
+
+    let mut inputs: VecDeque<i64> = vec![1, 2, 3].into();
+    let mut outputs = vec![];
+    let mut p = Process::new(code.clone());
+    assert!(matches!(
+        p.run_with(&mut inputs, &mut outputs),
+        Ok(Awaiting(_))
+    ));
+    assert_eq!(outputs, vec![1, 3, 6]);
+
+    let mut n = 0;
+    let mut total = 0;
+    let mut p = Process::new(code.clone());
+    p.run_with(
+        &mut || {
+            if n < 4 {
+                n += 1;
+                Some(n)
+            } else {
+                None
+            }
+        },
+        &mut |x| total = x,
+    )
+    .unwrap();
+    assert_eq!(total, 10);
+
+    let (tx, mut rx) = channel();
+    let (mut out_tx, out_rx) = channel();
+    tx.send(5).unwrap();
+    tx.send(6).unwrap();
+    drop(tx);
+    Process::new(code.clone())
+        .run_with(&mut rx, &mut out_tx)
+        .unwrap();
+    assert_eq!(out_rx.try_iter().collect::<Vec<_>>(), vec![5, 11]);
+
+    // nobody reads the outputs any more
+    let (mut out_tx, out_rx) = channel();
+    drop(out_rx);
+    let mut inputs: VecDeque<i64> = vec![5].into();
+    let mut p = Process::new(code.clone());
+    let err = p.run_with(&mut inputs, &mut out_tx).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::BrokenPipe);
+    assert_eq!(p.status(), Outputting(5));
+
+    let mut input = Numbers("7\n\nseven\n8\n".as_bytes());
+    let mut output = Numbers(vec![]);
+    let mut p = Process::new(code);
+    let err = p.run_with(&mut input, &mut output).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    assert!(matches!(p.status(), Awaiting(_)));
+    p.run_with(&mut input, &mut output).unwrap();
+    assert_eq!(output.0, b"7\n15\n");
+
+    let code = super::conformance::echo_until_zero();
+    let mut input = Ascii::new("hi\n".as_bytes());
+    let mut output = Ascii::new(vec![]);
+    let mut p = Process::new(code);
+    assert!(matches!(
+        p.run_with(&mut input, &mut output),
+        Ok(Awaiting(_))
+    ));
+    p.feed(0).unwrap();
+    assert_eq!(p.run_with(&mut input, &mut output).unwrap(), Exit);
+    assert_eq!(output.into_inner(), b"hi\n1000\n");
+}
@@ -0,0 +1,94 @@
+//! Comparing memories, to find where two runs diverge or what a program did
+//! to its own code.
+
+use super::disasm::{disassemble, Item, Line};
+use super::{Intcode, Memory, Process, Word};
+use std::collections::BTreeSet;
+use std::fmt;
+
+/// A cell holding different values on each side.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change<W> {
+    pub address: usize,
+    pub old: W,
+    pub new: W,
+    /// Instruction of the old memory covering the cell, if any.
+    pub instruction: Option<Line<W>>,
+}
+
+impl<W: Word> fmt::Display for Change<W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04}: {} -> {}", self.address, self.old, self.new)?;
+        if let Some(line) = &self.instruction {
+            write!(f, " (in {})", line)?;
+        }
+        Ok(())
+    }
+}
+
+/// Cells from address 0 up to the first one never written.
+fn prefix<W: Word>(memory: &Memory<W>) -> Intcode<W> {
+    let cells = memory
+        .cells()
+        .enumerate()
+        .take_while(|(i, (address, _))| i == address)
+        .map(|(_, (_, x))| x)
+        .collect();
+    Intcode(cells)
+}
+
+/// Cells that differ between `old` and `new`, in address order. Each
+/// change comes with the instruction covering it in the disassembly of
+/// `old`, see `disasm::disassemble`.
+pub fn diff<W: Word>(old: &Memory<W>, new: &Memory<W>) -> Vec<Change<W>> {
+    let addresses: BTreeSet<usize> = old.cells().chain(new.cells()).map(|(a, _)| a).collect();
+    let lines = disassemble(&prefix(old));
+    addresses
+        .into_iter()
+        .filter(|&a| old.get(a) != new.get(a))
+        .map(|address| {
+            let i = lines.partition_point(|l| l.address + l.size() <= address);
+            let instruction = lines
+                .get(i)
+                .filter(|l| l.address <= address && matches!(l.item, Item::Instruction(_)))
+                .cloned();
+            Change {
+                address,
+                old: old.get(address),
+                new: new.get(address),
+                instruction,
+            }
+        })
+        .collect()
+}
+
+impl<W: Word> Process<W> {
+    /// Cells whose values differ in `other`.
+    pub fn diff(&self, other: &Process<W>) -> Vec<Change<W>> {
+        diff(&self.memory, &other.memory)
+    }
+
+    /// Cells changed since the process started running `code`.
+    pub fn changes(&self, code: &Intcode<W>) -> Vec<Change<W>> {
+        diff(&Memory::dense(code.0.clone()), &self.memory)
+    }
+}
+
+#[test]
+pub fn diff_test() {
+    // doubles the operand of its own OUT, then writes past its end
+    let code: Intcode = "1002,9,2,9,1101,5,0,12,4,9,99".parse().unwrap();
+    let mut p = Process::new(code.clone());
+    p.run_until_blocked();
+    let changes = p.changes(&code);
+    let summary: Vec<String> = changes.iter().map(|c| c.to_string()).collect();
+    assert_eq!(
+        summary,
+        vec!["0009: 9 -> 18 (in 0008: OUT [9])", "0012: 0 -> 5"]
+    );
+
+    let mut q = Process::new(code);
+    assert_eq!(p.diff(&q).len(), 2);
+    q.run_until_blocked();
+    assert!(p.diff(&q).is_empty());
+}
@@ -0,0 +1,111 @@
+use super::Opcode::*;
+use super::Parameter::*;
+use super::{Intcode, Opcode, Parameter, Word};
+use std::convert::TryInto;
+use std::fmt;
+
+/// Contents of a memory cell (or run of cells) in a listing.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Item<W> {
+    Instruction(Opcode<Parameter<W>>),
+    Data(W),
+}
+
+/// One line of a listing, e.g. `0012: ADD [104], #3 -> [225]`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Line<W> {
+    pub address: usize,
+    pub item: Item<W>,
+}
+
+impl<W> Line<W> {
+    /// Number of cells covered by the line.
+    pub fn size(&self) -> usize {
+        match &self.item {
+            Item::Instruction(op) => op.size(),
+            Item::Data(_) => 1,
+        }
+    }
+}
+
+impl<W: Word> fmt::Display for Line<W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.item {
+            Item::Instruction(op) => write!(f, "{:04}: {}", self.address, op),
+            Item::Data(x) => write!(f, "{:04}: .data {}", self.address, x),
+        }
+    }
+}
+
+/// Decodes the instruction starting at `address` as the interpreter would.
+/// Returns `None` if the word there is not an instruction, writes to an
+/// immediate (which faults when run) or its parameters run past the end of
+/// `cells`.
+pub fn decode<W: Word>(cells: &[W], address: usize) -> Option<Opcode<Parameter<W>>> {
+    let word: i32 = (*cells.get(address)?).try_into().ok()?;
+    let code: Opcode<Parameter<()>> = word.try_into().ok()?;
+    let writes_immediate = matches!(
+        code,
+        Add(_, _, Imm(_)) | Mul(_, _, Imm(_)) | Lt(_, _, Imm(_)) | Equ(_, _, Imm(_)) | Inp(Imm(_))
+    );
+    if writes_immediate || address + code.size() > cells.len() {
+        return None;
+    }
+    let mut i = address;
+    Some(code.mut_map(&mut |m: &Parameter<()>| {
+        i += 1;
+        m.map(|_| cells[i])
+    }))
+}
+
+/// Whether `word` has mode digits for parameters `op` doesn't have. The
+/// interpreter ignores them, but an instruction can't be written with them.
+fn has_unused_modes<W: Word, A>(word: W, op: &Opcode<A>) -> bool {
+    let word: i32 = word.try_into().unwrap_or(0);
+    word / 10_i32.pow(op.size() as u32 + 1) != 0
+}
+
+/// Walks `code` from the start, decoding instructions where possible and
+/// treating any other word as data. Words with unused mode digits are data
+/// too, so a listing always reassembles to the same program.
+pub fn disassemble<W: Word>(code: &Intcode<W>) -> Vec<Line<W>> {
+    let cells = &code.0;
+    let mut lines = vec![];
+    let mut address = 0;
+    while address < cells.len() {
+        let decoded = decode(cells, address).filter(|op| !has_unused_modes(cells[address], op));
+        let item = match decoded {
+            Some(op) => Item::Instruction(op),
+            None => Item::Data(cells[address]),
+        };
+        let line = Line { address, item };
+        address += line.size();
+        lines.push(line);
+    }
+    lines
+}
+
+/// Disassembly of `code` as text, one line per instruction or data word.
+pub fn listing<W: Word>(code: &Intcode<W>) -> String {
+    disassemble(code)
+        .iter()
+        .map(|l| format!("{}\n", l))
+        .collect()
+}
+
+#[test]
+pub fn listing_test() {
+    let code: Intcode = "1002,104,3,225,203,-2,1105,1,0,99,1104".parse().unwrap();
+    let expected = "0000: MUL [104], #3 -> [225]\n\
+                    0004: INP -> [rb-2]\n\
+                    0006: JNZ #1, #0\n\
+                    0009: HLT\n\
+                    0010: .data 1104\n";
+    assert_eq!(listing(&code), expected);
+    // the assembler won't take an immediate write operand
+    let code = Intcode(vec![11101, 1, 2, 3]);
+    assert_eq!(listing(&code).lines().next(), Some("0000: .data 11101"));
+    // ...nor mode digits for parameters an instruction doesn't have
+    let code: Intcode = "10104,5,99".parse().unwrap();
+    assert_eq!(listing(&code).lines().next(), Some("0000: .data 10104"));
+}
@@ -0,0 +1,117 @@
+use super::Word;
+use std::error::Error;
+use std::fmt;
+
+/// Reason an instruction word could not be decoded into an `Opcode`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodeError {
+    UnknownOpcode(i32),
+    UnknownMode(i32),
+}
+
+/// Fault raised by a running process. Every variant carries the program
+/// counter and raw word of the instruction that caused it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IntcodeError<W = i64> {
+    UnknownOpcode {
+        pc: usize,
+        instruction: W,
+    },
+    UnknownMode {
+        pc: usize,
+        instruction: W,
+        mode: i32,
+    },
+    WriteToImmediate {
+        pc: usize,
+        instruction: W,
+    },
+    NegativeAddress {
+        pc: usize,
+        instruction: W,
+        address: W,
+    },
+    OutOfBounds {
+        pc: usize,
+        instruction: W,
+        address: W,
+        limit: usize,
+    },
+    NotAwaiting {
+        pc: usize,
+        instruction: W,
+    },
+    /// A result, address or relative base too large for a word.
+    Overflow {
+        pc: usize,
+        instruction: W,
+    },
+}
+use IntcodeError::*;
+
+impl<W: Word> IntcodeError<W> {
+    pub fn decode(pc: usize, instruction: W, err: DecodeError) -> Self {
+        match err {
+            DecodeError::UnknownOpcode(_) => UnknownOpcode { pc, instruction },
+            DecodeError::UnknownMode(mode) => UnknownMode {
+                pc,
+                instruction,
+                mode,
+            },
+        }
+    }
+
+    pub fn pc(&self) -> usize {
+        match *self {
+            UnknownOpcode { pc, .. }
+            | UnknownMode { pc, .. }
+            | WriteToImmediate { pc, .. }
+            | NegativeAddress { pc, .. }
+            | OutOfBounds { pc, .. }
+            | NotAwaiting { pc, .. }
+            | Overflow { pc, .. } => pc,
+        }
+    }
+
+    pub fn instruction(&self) -> W {
+        match *self {
+            UnknownOpcode { instruction, .. }
+            | UnknownMode { instruction, .. }
+            | WriteToImmediate { instruction, .. }
+            | NegativeAddress { instruction, .. }
+            | OutOfBounds { instruction, .. }
+            | NotAwaiting { instruction, .. }
+            | Overflow { instruction, .. } => instruction,
+        }
+    }
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::UnknownOpcode(o) => write!(f, "Unknown operation {}", o),
+            DecodeError::UnknownMode(m) => write!(f, "Unknown mode {}", m),
+        }
+    }
+}
+
+impl Error for DecodeError {}
+
+impl<W: Word> fmt::Display for IntcodeError<W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04} ({}): ", self.pc(), self.instruction())?;
+        match self {
+            UnknownOpcode { .. } => write!(f, "unknown operation"),
+            UnknownMode { mode, .. } => write!(f, "unknown mode {}", mode),
+            WriteToImmediate { .. } => write!(f, "can't write to an immediate value"),
+            NegativeAddress { address, .. } => write!(f, "negative address {}", address),
+            OutOfBounds { address, limit, .. } => {
+                write!(f, "address {} exceeds memory limit {}", address, limit)
+            }
+            NotAwaiting { .. } => write!(f, "fed while not awaiting input"),
+            Overflow { .. } => write!(f, "arithmetic overflow"),
+        }
+    }
+}
+
+impl<W: Word> Error for IntcodeError<W> {}
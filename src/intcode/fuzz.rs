@@ -0,0 +1,404 @@
+//! Differential fuzzing: random programs are run both by `Process`, in each
+//! of its configurations, and by a deliberately simple reference
+//! interpreter, and any difference is shrunk to a small program.
+//!
+//! ```text
+//! cargo run --release -- fuzz 100000 7
+//! ```
+//!
+//! Programs are well-formed to start with, but write into their own code
+//! freely, so they soon run into self-modified and invalid instructions.
+
+use super::memory::DENSE_LIMIT;
+use super::{Intcode, Process, ProcessStatus::*};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fmt;
+
+/// Instructions run before giving up on a program.
+const STEPS: u64 = 1000;
+
+/// Xorshift generator, good enough to pick programs.
+#[derive(Debug, Clone)]
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng((seed ^ 0x9e37_79b9_7f4a_7c15).max(1))
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        let mut x = self.0;
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        self.0 = x;
+        x.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// A number in `0..n`.
+    pub fn below(&mut self, n: u64) -> u64 {
+        self.next_u64() % n
+    }
+
+    /// A number in `lo..hi`.
+    pub fn range(&mut self, lo: i64, hi: i64) -> i64 {
+        lo + self.below((hi - lo) as u64) as i64
+    }
+
+    pub fn pick<T: Copy>(&mut self, xs: &[T]) -> T {
+        xs[self.below(xs.len() as u64) as usize]
+    }
+}
+
+/// A random program: a few instructions of any kind, a `HLT` and some data.
+pub fn program(rng: &mut Rng) -> Intcode {
+    let n = rng.range(1, 16) as usize;
+    let mut ops: Vec<i64> = (0..n).map(|_| rng.range(1, 10)).collect();
+    ops.push(99);
+    let size = |op: i64| match op {
+        1 | 2 | 7 | 8 => 4,
+        5 | 6 => 3,
+        99 => 1,
+        _ => 2,
+    };
+    let mut starts = vec![];
+    let mut len = 0;
+    for &op in &ops {
+        starts.push(len as i64);
+        len += size(op);
+    }
+    let len = (len + rng.below(4)) as i64;
+
+    let mut code = vec![];
+    for &op in &ops {
+        let params = size(op) - 1;
+        let mut word = op;
+        let mut operands = vec![];
+        for i in 0..params {
+            let writes = i == 2 || op == 3;
+            let mode = if writes {
+                rng.pick(&[0, 2])
+            } else {
+                rng.pick(&[0, 1, 2])
+            };
+            let value = match mode {
+                0 => rng.range(0, len + 4),
+                2 => rng.range(-4, len + 4),
+                _ if i == 1 && (op == 5 || op == 6) => rng.pick(&starts),
+                _ if op == 9 => rng.range(-3, 4),
+                // now and then large enough to overflow
+                _ if rng.below(8) == 0 => rng.pick(&[i64::MIN, -(1 << 32), 1 << 32, i64::MAX]),
+                _ => rng.range(-10, 11),
+            };
+            word += mode * 10_i64.pow(i as u32 + 2);
+            operands.push(value);
+        }
+        code.push(word);
+        code.extend(operands);
+    }
+    while (code.len() as i64) < len {
+        code.push(rng.range(-10, 11));
+    }
+    Intcode(code)
+}
+
+pub fn inputs(rng: &mut Rng) -> Vec<i64> {
+    let n = rng.below(5);
+    (0..n).map(|_| rng.range(-10, 11)).collect()
+}
+
+/// How a run ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum End {
+    Halt,
+    /// Faulted at this address.
+    Fault(usize),
+    /// Asked for more input than given.
+    Input,
+    OutOfSteps,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Outcome {
+    pub outputs: Vec<i64>,
+    pub end: End,
+}
+
+/// The reference interpreter, written straight from the puzzle text plus
+/// the rules this interpreter adds: instructions fit in 32 bits, every mode
+/// digit must be 0, 1 or 2, addresses are below `DENSE_LIMIT` and arithmetic
+/// overflow is a fault.
+struct Reference {
+    memory: HashMap<usize, i64>,
+    pc: usize,
+    rb: i64,
+}
+
+impl Reference {
+    fn get(&self, address: usize) -> i64 {
+        *self.memory.get(&address).unwrap_or(&0)
+    }
+
+    fn check(&self, address: i64) -> Result<usize, End> {
+        if address < 0 || address >= DENSE_LIMIT as i64 {
+            return Err(End::Fault(self.pc));
+        }
+        Ok(address as usize)
+    }
+
+    fn address(&self, mode: i64, p: i64) -> Result<usize, End> {
+        match mode {
+            0 => self.check(p),
+            2 => self.check(self.rb.checked_add(p).ok_or(End::Fault(self.pc))?),
+            _ => Err(End::Fault(self.pc)),
+        }
+    }
+
+    fn read(&self, mode: i64, p: i64) -> Result<i64, End> {
+        match mode {
+            1 => Ok(p),
+            _ => Ok(self.get(self.address(mode, p)?)),
+        }
+    }
+
+    fn write(&mut self, mode: i64, p: i64, value: i64) -> Result<(), End> {
+        let address = self.address(mode, p)?;
+        self.memory.insert(address, value);
+        Ok(())
+    }
+
+    fn run(&mut self, inputs: &[i64], outputs: &mut Vec<i64>) -> Result<(), End> {
+        let mut inputs = inputs.iter();
+        for _ in 0..STEPS {
+            let pc = self.pc;
+            let x = self.get(pc);
+            if i32::try_from(x).is_err() {
+                return Err(End::Fault(pc));
+            }
+            let modes = [x / 100 % 10, x / 1000 % 10, x / 10000 % 10];
+            if modes.iter().any(|m| !(0..=2).contains(m)) {
+                return Err(End::Fault(pc));
+            }
+            let p = [self.get(pc + 1), self.get(pc + 2), self.get(pc + 3)];
+            let arg = |r: &Self, i: usize| r.read(modes[i], p[i]);
+            match x % 100 {
+                1 | 2 | 7 | 8 => {
+                    let (a, b) = (arg(self, 0)?, arg(self, 1)?);
+                    let value = match x % 100 {
+                        1 => a.checked_add(b).ok_or(End::Fault(pc))?,
+                        2 => a.checked_mul(b).ok_or(End::Fault(pc))?,
+                        7 => (a < b) as i64,
+                        _ => (a == b) as i64,
+                    };
+                    self.write(modes[2], p[2], value)?;
+                    self.pc += 4;
+                }
+                3 => {
+                    let address = self.address(modes[0], p[0])?;
+                    let input = *inputs.next().ok_or(End::Input)?;
+                    self.memory.insert(address, input);
+                    self.pc += 2;
+                }
+                4 => {
+                    outputs.push(arg(self, 0)?);
+                    self.pc += 2;
+                }
+                5 | 6 => {
+                    let jump = (arg(self, 0)? != 0) == (x % 100 == 5);
+                    if jump {
+                        self.pc = self.check(arg(self, 1)?)?;
+                    } else {
+                        self.pc += 3;
+                    }
+                }
+                9 => {
+                    let rb = self.rb.checked_add(arg(self, 0)?);
+                    self.rb = rb.ok_or(End::Fault(pc))?;
+                    self.pc += 2;
+                }
+                99 => return Err(End::Halt),
+                _ => return Err(End::Fault(pc)),
+            }
+        }
+        Err(End::OutOfSteps)
+    }
+}
+
+/// Runs `code` on the reference interpreter.
+pub fn reference(code: &[i64], inputs: &[i64]) -> Outcome {
+    let mut r = Reference {
+        memory: code.iter().copied().enumerate().collect(),
+        pc: 0,
+        rb: 0,
+    };
+    let mut outputs = vec![];
+    let end = r.run(inputs, &mut outputs).unwrap_err();
+    Outcome { outputs, end }
+}
+
+/// The ways of setting up a process that should all behave the same.
+const CONFIGS: [&str; 3] = ["cached", "uncached", "history"];
+
+fn run(config: &str, code: &[i64], inputs: &[i64]) -> Outcome {
+    let p = Process::new(Intcode(code.to_vec()))
+        .with_inputs(inputs.iter().copied())
+        .with_budget(STEPS);
+    let mut p = match config {
+        "uncached" => p.without_cache(),
+        "history" => p.with_history(8),
+        _ => p,
+    };
+    let end = match p.run_until_blocked() {
+        Exit => End::Halt,
+        Faulted(e) => End::Fault(e.pc()),
+        Awaiting(_) => End::Input,
+        Exhausted => End::OutOfSteps,
+        s => panic!("unexpected status {:?}", s),
+    };
+    Outcome {
+        outputs: p.take_output(),
+        end,
+    }
+}
+
+/// A program on which a configuration of `Process` disagrees with the
+/// reference interpreter.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    pub config: &'static str,
+    pub code: Intcode,
+    pub inputs: Vec<i64>,
+    pub expected: Outcome,
+    pub actual: Outcome,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let join = |xs: &[i64]| {
+            let xs: Vec<String> = xs.iter().map(|x| x.to_string()).collect();
+            xs.join(",")
+        };
+        writeln!(f, "{} process diverges from the reference", self.config)?;
+        writeln!(f, "program:  {}", join(&self.code.0))?;
+        writeln!(f, "inputs:   {}", join(&self.inputs))?;
+        writeln!(f, "expected: {:?}", self.expected)?;
+        writeln!(f, "actual:   {:?}", self.actual)
+    }
+}
+
+/// Compares every configuration with the reference on one program.
+pub fn check(code: &[i64], inputs: &[i64]) -> Option<Divergence> {
+    let expected = reference(code, inputs);
+    CONFIGS.iter().find_map(|&config| {
+        let actual = run(config, code, inputs);
+        if actual == expected {
+            return None;
+        }
+        Some(Divergence {
+            config,
+            code: Intcode(code.to_vec()),
+            inputs: inputs.to_vec(),
+            expected: expected.clone(),
+            actual,
+        })
+    })
+}
+
+/// Removes elements of `xs` one at a time while `fails` still holds.
+fn remove_cells<F: FnMut(&[i64]) -> bool>(xs: &mut Vec<i64>, mut fails: F) -> bool {
+    let mut progress = false;
+    let mut i = 0;
+    while i < xs.len() {
+        let mut candidate = xs.clone();
+        candidate.remove(i);
+        if fails(&candidate) {
+            *xs = candidate;
+            progress = true;
+        } else {
+            i += 1;
+        }
+    }
+    progress
+}
+
+/// Shrinks a program and its inputs while `fails` still holds, removing
+/// cells and moving values towards zero.
+pub fn minimize<F>(mut code: Vec<i64>, mut inputs: Vec<i64>, mut fails: F) -> (Vec<i64>, Vec<i64>)
+where
+    F: FnMut(&[i64], &[i64]) -> bool,
+{
+    loop {
+        let mut progress = remove_cells(&mut code, |c| fails(c, &inputs));
+        progress |= remove_cells(&mut inputs, |i| fails(&code, i));
+        for i in 0..code.len() {
+            let x = code[i];
+            for &smaller in &[0, x / 2] {
+                if smaller == x {
+                    continue;
+                }
+                let mut candidate = code.clone();
+                candidate[i] = smaller;
+                if fails(&candidate, &inputs) {
+                    code = candidate;
+                    progress = true;
+                    break;
+                }
+            }
+        }
+        if !progress {
+            return (code, inputs);
+        }
+    }
+}
+
+/// Checks `cases` random programs, returning the first divergence found,
+/// minimized.
+pub fn fuzz(seed: u64, cases: usize) -> Option<Divergence> {
+    let mut rng = Rng::new(seed);
+    for _ in 0..cases {
+        let code = program(&mut rng);
+        let inputs = inputs(&mut rng);
+        if check(&code.0, &inputs).is_some() {
+            let (code, inputs) = minimize(code.0, inputs, |c, i| check(c, i).is_some());
+            return check(&code, &inputs);
+        }
+    }
+    None
+}
+
+#[test]
+pub fn fuzz_test() {
+    if let Some(divergence) = fuzz(1, 2000) {
+        panic!("{}", divergence);
+    }
+}
+
+#[test]
+pub fn reference_test() {
+    let quine = "109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99";
+    let code: Intcode = quine.parse().unwrap();
+    let outcome = reference(&code.0, &[]);
+    assert_eq!(outcome.outputs, code.0);
+    assert_eq!(outcome.end, End::Halt);
+    assert_eq!(reference(&[3, 0, 99], &[]).end, End::Input);
+    assert_eq!(reference(&[1105, 1, 0], &[]).end, End::OutOfSteps);
+    assert_eq!(reference(&[1, 0, 0, -1, 99], &[]).end, End::Fault(0));
+    assert_eq!(reference(&[399], &[]).end, End::Fault(0));
+    let overflow = [109, i64::MAX, 204, 1, 99];
+    assert_eq!(reference(&overflow, &[]).end, End::Fault(2));
+    assert_eq!(check(&overflow, &[]), None);
+}
+
+#[test]
+pub fn minimize_test() {
+    // prints 42 somewhere among other noise
+    let code = vec![104, 1, 1101, 40, 2, 13, 4, 13, 104, 3, 99, 7, 7, 0];
+    let prints_42 = |c: &[i64], i: &[i64]| reference(c, i).outputs.contains(&42);
+    assert!(prints_42(&code, &[5]));
+    let (code, inputs) = minimize(code, vec![5], prints_42);
+    assert!(prints_42(&code, &inputs));
+    assert!(inputs.is_empty());
+    // cells are removed one at a time, so the first OUT can't go
+    assert_eq!(code, vec![104, 0, 1101, 40, 2, 13, 4, 13]);
+}
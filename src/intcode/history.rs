@@ -0,0 +1,184 @@
+//! Stepping a process backwards. With a history, every instruction run (and
+//! every input fed) saves what it is about to change, so it can be undone.
+//! Instructions that don't get to run, because they fault, wait for input,
+//! are out of budget or close an infinite loop, leave nothing to undo.
+//!
+//! Queued inputs an instruction consumed are put back when it is undone, but
+//! outputs already handed to the caller or collected by `run_until_blocked`
+//! stay where they are.
+
+use super::{Process, ProcessStatus, Word};
+use std::collections::VecDeque;
+
+/// State before an instruction ran.
+#[derive(Debug, Clone)]
+pub(super) struct Undo<W> {
+    pc: usize,
+    rb: W,
+    status: ProcessStatus<W>,
+    budget: Option<u64>,
+    /// Previous values of the cells written, in order.
+    writes: Vec<(usize, W)>,
+    /// Input taken from the queue.
+    input: Option<W>,
+}
+
+#[derive(Debug, Clone)]
+pub(super) struct History<W> {
+    undos: VecDeque<Undo<W>>,
+    limit: usize,
+    /// Undo for the instruction being run, kept if it runs to the end.
+    pending: Option<Undo<W>>,
+}
+
+impl<W: Word> Process<W> {
+    /// Keeps enough history to step back up to `limit` instructions.
+    pub fn with_history(mut self, limit: usize) -> Self {
+        self.history = Some(History {
+            undos: VecDeque::new(),
+            limit,
+            pending: None,
+        });
+        self
+    }
+
+    /// How many instructions can be undone.
+    pub fn history_len(&self) -> usize {
+        self.history.as_ref().map_or(0, |h| h.undos.len())
+    }
+
+    /// Saves the state before running an instruction or feeding input, with
+    /// the status to go back to, until `commit` tells whether it ran.
+    pub(super) fn checkpoint(&mut self, status: ProcessStatus<W>) {
+        let undo = Undo {
+            pc: self.pc,
+            rb: self.rb,
+            status,
+            budget: self.budget,
+            writes: vec![],
+            input: None,
+        };
+        if let Some(history) = &mut self.history {
+            history.pending = Some(undo);
+        }
+    }
+
+    /// Adds the last checkpoint to the history if `ran`, or drops it.
+    pub(super) fn commit(&mut self, ran: bool) {
+        if let Some(history) = &mut self.history {
+            match history.pending.take() {
+                Some(undo) if ran && history.limit > 0 => {
+                    if history.undos.len() == history.limit {
+                        history.undos.pop_front();
+                    }
+                    history.undos.push_back(undo);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    pub(super) fn record_write(&mut self, x: usize) {
+        let old = self.memory.get(x);
+        let pending = self.history.as_mut().and_then(|h| h.pending.as_mut());
+        if let Some(undo) = pending {
+            undo.writes.push((x, old));
+        }
+    }
+
+    pub(super) fn record_input(&mut self, input: W) {
+        let pending = self.history.as_mut().and_then(|h| h.pending.as_mut());
+        if let Some(undo) = pending {
+            undo.input = Some(input);
+        }
+    }
+
+    /// Undoes the last instruction, or the last input fed. Returns `false`
+    /// if there is no history left.
+    pub fn step_back(&mut self) -> bool {
+        let undo = match self.history.as_mut().and_then(|h| h.undos.pop_back()) {
+            Some(undo) => undo,
+            None => return false,
+        };
+        for &(x, old) in undo.writes.iter().rev() {
+            self.write(x, old);
+        }
+        if let Some(x) = undo.input {
+            self.inputs.push_front(x);
+        }
+        self.pc = undo.pc;
+        self.rb = undo.rb;
+        self.status = undo.status;
+        self.budget = undo.budget;
+        self.forget_states();
+        true
+    }
+
+    /// Steps back until the pc is `pc`, at least once. Returns `false`, with
+    /// the whole history undone, if it never gets there.
+    pub fn run_back_to(&mut self, pc: usize) -> bool {
+        while self.step_back() {
+            if self.pc == pc {
+                return true;
+            }
+        }
+        false
+    }
+}
+
+#[test]
+pub fn history_test() {
+    use super::Intcode;
+    use super::ProcessStatus::*;
+    let code = super::conformance::running_total();
+    let mut p = Process::new(code).with_history(100).with_inputs([1, 2]);
+    assert_eq!(p.resume(), Outputting(1));
+    assert_eq!(p.resume(), Outputting(3));
+    assert_eq!(p.history_len(), 7);
+
+    // back to before the second addition
+    assert!(p.run_back_to(2));
+    assert_eq!(p.memory().get(11), 2);
+    assert_eq!(p.memory().get(12), 1);
+    assert!(p.step_back());
+    assert_eq!(p.pc(), 0);
+    assert_eq!(p.memory().get(11), 1);
+    assert_eq!(p.resume(), Outputting(3));
+
+    // an input fed is undone on its own, and queued inputs are put back
+    assert!(matches!(p.resume(), Awaiting(_)));
+    p.feed(10).unwrap();
+    assert!(p.step_back());
+    assert!(matches!(p.status(), Awaiting(_)));
+    assert_eq!(p.memory().get(11), 2);
+    p.feed(10).unwrap();
+    assert_eq!(p.resume(), Outputting(13));
+    assert!(p.run_back_to(0));
+    assert!(matches!(p.status(), Awaiting(_)));
+    assert!(p.run_back_to(0));
+    assert_eq!(p.resume(), Outputting(3));
+    assert!(!p.run_back_to(5));
+    assert_eq!(p.pc(), 0);
+    assert_eq!(p.memory().get(12), 0);
+    assert_eq!(p.resume(), Outputting(1));
+
+    let code: Intcode = "1101,1,1,5,99,0".parse().unwrap();
+    let mut p = Process::new(code).with_history(1);
+    assert_eq!(p.resume(), Exit);
+    assert_eq!(p.history_len(), 1);
+    assert!(p.step_back());
+    assert!(!p.step_back());
+    assert_eq!(p.memory().get(5), 2);
+
+    // neither a fault nor running out of budget is undone
+    let code: Intcode = "1101,1,1,8,1,0,0,-1,0".parse().unwrap();
+    let mut p = Process::new(code).with_history(10).with_budget(1);
+    assert_eq!(p.resume(), Exhausted);
+    assert_eq!(p.history_len(), 1);
+    p.set_budget(None);
+    assert!(matches!(p.resume(), Faulted(_)));
+    assert_eq!(p.history_len(), 1);
+    assert!(p.step_back());
+    assert_eq!((p.pc(), p.status(), p.budget()), (0, Paused, Some(1)));
+    assert_eq!(p.memory().get(8), 0);
+}
@@ -1,14 +1,14 @@
-use std::num::ParseIntError;
+use super::Word;
 
 #[derive(Debug, PartialEq, Eq, Clone)]
-pub struct Intcode(pub Vec<i32>);
+pub struct Intcode<W = i64>(pub Vec<W>);
 
 use std::str::FromStr;
-impl FromStr for Intcode {
+impl<W: Word> FromStr for Intcode<W> {
     type Err = String;
 
-    fn from_str(input: &str) -> Result<Intcode, Self::Err> {
-        let res: Result<Vec<i32>, ParseIntError> = input.split(",").map(|x| x.parse()).collect();
+    fn from_str(input: &str) -> Result<Intcode<W>, Self::Err> {
+        let res: Result<Vec<W>, _> = input.trim().split(',').map(|x| x.parse()).collect();
         match res {
             Ok(v) => Ok(Intcode(v)),
             Err(_) => Err("Invalid intcode".to_string()),
@@ -16,17 +16,14 @@ impl FromStr for Intcode {
     }
 }
 
-use std::convert::Into;
-impl Into<Vec<i32>> for Intcode {
-    fn into(self) -> Vec<i32> {
-        match self {
-            Intcode(c) => c,
-        }
+impl<W> From<Intcode<W>> for Vec<W> {
+    fn from(code: Intcode<W>) -> Vec<W> {
+        code.0
     }
 }
 
-impl Intcode {
-    pub fn replace(&self, noun: i32, verb: i32) -> Intcode {
+impl<W: Word> Intcode<W> {
+    pub fn replace(&self, noun: W, verb: W) -> Intcode<W> {
         let Intcode(arr) = self;
         let mut newarr = arr.clone();
         newarr[1] = noun;
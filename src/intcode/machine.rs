@@ -1,22 +1,126 @@
-use intcode::Process;
+use super::device::{InputDevice, OutputDevice};
+use super::{Intcode, IntcodeError, Process, ProcessStatus, Word};
+use std::io;
 
-enum MachineStatus {
+/// Why a machine stopped running.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MachineStatus<W = i64> {
+    /// Stopped by the predicate given to `run_until`.
     Paused,
-    Outputing(i32),
+    /// The program asked for input the provider didn't have.
     Listening,
     Stop,
+    Faulted(IntcodeError<W>),
+    /// Ran out of instruction budget.
+    Exhausted,
+    /// Stuck in an infinite loop at the given address.
+    Looping(usize),
 }
+use MachineStatus::*;
 
-struct Machine {
-    process: Process,
-    status: MachineStatus,
+/// A process wired to an input and an output device, so it can be run
+/// without handling each input request and output by hand.
+pub struct Machine<'a, W = i64> {
+    process: Process<W>,
+    status: MachineStatus<W>,
+    input: Box<dyn InputDevice<W> + 'a>,
+    output: Box<dyn OutputDevice<W> + 'a>,
 }
 
-impl Machine {
-    pub fn new(code: Intcode) -> Machine {
+impl<'a, W: Word> Machine<'a, W> {
+    /// A machine without input whose outputs are dropped.
+    pub fn new(code: Intcode<W>) -> Self {
+        Self::with_process(Process::new(code))
+    }
+
+    pub fn with_process(process: Process<W>) -> Self {
         Machine {
-            process: Process::new(code),
+            process,
             status: Paused,
+            input: Box::new(|| None),
+            output: Box::new(|_| ()),
         }
     }
+
+    /// Sets the device read whenever the program asks for input, such as a
+    /// closure. Running out of input stops the machine as `Listening`.
+    pub fn input<I: InputDevice<W> + 'a>(mut self, input: I) -> Self {
+        self.input = Box::new(input);
+        self
+    }
+
+    /// Sets the device every output is written to.
+    pub fn output<O: OutputDevice<W> + 'a>(mut self, output: O) -> Self {
+        self.output = Box::new(output);
+        self
+    }
+
+    pub fn process(&self) -> &Process<W> {
+        &self.process
+    }
+
+    pub fn into_process(self) -> Process<W> {
+        self.process
+    }
+
+    pub fn status(&self) -> MachineStatus<W> {
+        self.status
+    }
+
+    /// Runs until the program halts, faults or runs out of input. Fails if
+    /// either device does.
+    pub fn run(&mut self) -> io::Result<MachineStatus<W>> {
+        self.run_until(|_| false)
+    }
+
+    /// Runs like `run`, but also stops as `Paused` once `pred` holds. The
+    /// predicate is checked after every input and output.
+    pub fn run_until<F>(&mut self, mut pred: F) -> io::Result<MachineStatus<W>>
+    where
+        F: FnMut(&Process<W>) -> bool,
+    {
+        self.status = loop {
+            match self.process.resume() {
+                ProcessStatus::Paused => continue,
+                ProcessStatus::Outputting(x) => self.output.write(x)?,
+                ProcessStatus::Awaiting(_) => match self.input.read()? {
+                    Some(x) => {
+                        self.process.feed(x).expect("process is awaiting input");
+                    }
+                    None => break Listening,
+                },
+                ProcessStatus::Exit => break Stop,
+                ProcessStatus::Faulted(e) => break Faulted(e),
+                ProcessStatus::Exhausted => break Exhausted,
+                ProcessStatus::Looping(pc) => break Looping(pc),
+            }
+            if pred(&self.process) {
+                break Paused;
+            }
+        };
+        Ok(self.status)
+    }
+}
+
+#[test]
+pub fn machine_test() {
+    let code = super::conformance::running_total();
+    let mut inputs = vec![1, 2, 3].into_iter();
+    let mut outputs = vec![];
+    let mut m = Machine::new(code.clone())
+        .input(|| inputs.next())
+        .output(|x| outputs.push(x));
+    assert_eq!(m.run().unwrap(), Listening);
+    drop(m);
+    assert_eq!(outputs, vec![1, 3, 6]);
+
+    let mut count = 0;
+    let mut m = Machine::new(code).input(|| Some(10)).output(|_| count += 1);
+    assert_eq!(m.run_until(|p| p.memory().get(12) >= 50).unwrap(), Paused);
+    assert_eq!(m.process().memory().get(12), 50);
+    drop(m);
+    assert_eq!(count, 5);
+
+    let code: Intcode = "104,7,99".parse().unwrap();
+    assert_eq!(Machine::new(code).run().unwrap(), Stop);
 }
@@ -0,0 +1,115 @@
+use super::Word;
+use std::collections::BTreeMap;
+
+/// Highest number of cells a dense memory grows to unless told otherwise.
+pub const DENSE_LIMIT: usize = 1 << 20;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Cells<W> {
+    Dense(Vec<W>),
+    Sparse(BTreeMap<usize, W>),
+}
+use Cells::*;
+
+/// Memory of a process. Untouched addresses read as zero and writing past the
+/// end grows it, up to `limit` addresses.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Memory<W> {
+    cells: Cells<W>,
+    limit: usize,
+}
+
+impl<W: Word> Memory<W> {
+    /// Memory backed by a vector, for programs that stay near their own code.
+    pub fn dense(code: Vec<W>) -> Self {
+        Memory {
+            cells: Dense(code),
+            limit: DENSE_LIMIT,
+        }
+    }
+
+    /// Memory backed by a map, for programs that poke at very high addresses.
+    pub fn sparse(code: Vec<W>) -> Self {
+        Memory {
+            cells: Sparse(code.into_iter().enumerate().collect()),
+            limit: usize::MAX,
+        }
+    }
+
+    pub fn with_limit(mut self, limit: usize) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// One past the highest address that has been written.
+    pub fn len(&self) -> usize {
+        match &self.cells {
+            Dense(v) => v.len(),
+            Sparse(m) => m.keys().next_back().map_or(0, |k| k + 1),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn is_sparse(&self) -> bool {
+        match self.cells {
+            Dense(_) => false,
+            Sparse(_) => true,
+        }
+    }
+
+    /// Every stored cell with its address, in address order.
+    pub fn cells<'a>(&'a self) -> Box<dyn Iterator<Item = (usize, W)> + 'a> {
+        match &self.cells {
+            Dense(v) => Box::new(v.iter().copied().enumerate()),
+            Sparse(m) => Box::new(m.iter().map(|(a, x)| (*a, *x))),
+        }
+    }
+
+    pub fn get(&self, addr: usize) -> W {
+        let cell = match &self.cells {
+            Dense(v) => v.get(addr),
+            Sparse(m) => m.get(&addr),
+        };
+        cell.copied().unwrap_or_else(|| W::from(0))
+    }
+
+    /// Writes `value` at `addr`, growing the memory if needed. Fails with the
+    /// memory limit when `addr` is past it.
+    pub fn set(&mut self, addr: usize, value: W) -> Result<(), usize> {
+        if addr >= self.limit {
+            return Err(self.limit);
+        }
+        match &mut self.cells {
+            Dense(v) => {
+                if addr >= v.len() {
+                    v.resize(addr + 1, W::from(0));
+                }
+                v[addr] = value;
+            }
+            Sparse(m) => {
+                m.insert(addr, value);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[test]
+pub fn memory_test() {
+    let mut dense = Memory::dense(vec![1, 2, 3]).with_limit(10);
+    assert_eq!(dense.get(100), 0);
+    dense.set(5, 7).unwrap();
+    assert_eq!((dense.len(), dense.get(4), dense.get(5)), (6, 0, 7));
+    assert!(dense.set(10, 1).is_err());
+
+    let mut sparse = Memory::sparse(vec![1, 2, 3]);
+    sparse.set(1 << 40, 9).unwrap();
+    assert_eq!((sparse.len(), sparse.get(1 << 40)), ((1 << 40) + 1, 9));
+}
@@ -1,13 +1,36 @@
+pub mod ascii;
+pub mod asm;
+pub mod cfg;
+#[cfg(test)]
+mod conformance;
+pub mod debugger;
+pub mod device;
+pub mod diff;
+pub mod disasm;
+pub mod error;
+pub mod fuzz;
+mod history;
 pub mod intcode;
+pub mod machine;
+pub mod memory;
+pub mod profile;
+pub mod snapshot;
+pub mod trace;
+pub mod word;
 
+pub use error::{DecodeError, IntcodeError};
 pub use intcode::Intcode;
+pub use memory::Memory;
+pub use word::Word;
 
+use std::collections::VecDeque;
 use std::convert::{TryFrom, TryInto};
 use std::fmt;
+use IntcodeError::*;
 
 // OPCODE
 use Opcode::*;
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum Opcode<A> {
     Add(A, A, A),
     Mul(A, A, A),
@@ -17,6 +40,7 @@ pub enum Opcode<A> {
     Jz(A, A),
     Lt(A, A, A),
     Equ(A, A, A),
+    Arb(A),
     Hlt,
 }
 
@@ -25,29 +49,32 @@ use Parameter::*;
 pub enum Parameter<A> {
     Pos(A),
     Imm(A),
+    Rel(A),
 }
 
 impl TryFrom<i32> for Parameter<()> {
-    type Error = String;
+    type Error = DecodeError;
 
     fn try_from(item: i32) -> Result<Self, Self::Error> {
         match item {
             0 => Ok(Pos(())),
             1 => Ok(Imm(())),
-            m => Err(format!("Unknown mode {:?}", m).to_string()),
+            2 => Ok(Rel(())),
+            m => Err(DecodeError::UnknownMode(m)),
         }
     }
 }
 
 impl<T> Parameter<T> {
-    #[allow(dead_code)]
     fn map<B, F: Fn(&T) -> B>(&self, f: F) -> Parameter<B> {
         match self {
             Pos(x) => Pos(f(x)),
             Imm(x) => Imm(f(x)),
+            Rel(x) => Rel(f(x)),
         }
     }
 
+    #[allow(dead_code)]
     fn try_map<B, E, F>(&self, f: F) -> Result<Parameter<B>, E>
     where
         F: Fn(&T) -> Result<B, E>,
@@ -55,10 +82,22 @@ impl<T> Parameter<T> {
         Ok(match self {
             Pos(x) => Pos(f(x)?),
             Imm(x) => Imm(f(x)?),
+            Rel(x) => Rel(f(x)?),
         })
     }
 }
 
+impl<W: Word> fmt::Display for Parameter<W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Pos(x) => write!(f, "[{}]", x),
+            Imm(x) => write!(f, "#{}", x),
+            Rel(x) if *x < W::from(0) => write!(f, "[rb{}]", x),
+            Rel(x) => write!(f, "[rb+{}]", x),
+        }
+    }
+}
+
 fn digit(x: i32, i: u32) -> i32 {
     x / 10_i32.pow(i) % 10
 }
@@ -74,6 +113,7 @@ impl<T> Opcode<T> {
             Jz(a, b) => Jz(f(a), f(b)),
             Lt(a, b, c) => Lt(f(a), f(b), f(c)),
             Equ(a, b, c) => Equ(f(a), f(b), f(c)),
+            Arb(a) => Arb(f(a)),
             _ => Hlt,
         }
     }
@@ -89,13 +129,56 @@ impl<T> Opcode<T> {
             Jz(a, b) => Jz(f(a), f(b)),
             Lt(a, b, c) => Lt(f(a), f(b), f(c)),
             Equ(a, b, c) => Equ(f(a), f(b), f(c)),
+            Arb(a) => Arb(f(a)),
             _ => Hlt,
         }
     }
 }
 
+impl<T> Opcode<T> {
+    pub fn mnemonic(&self) -> &'static str {
+        match self {
+            Add(..) => "ADD",
+            Mul(..) => "MUL",
+            Inp(..) => "INP",
+            Out(..) => "OUT",
+            Jnz(..) => "JNZ",
+            Jz(..) => "JZ",
+            Lt(..) => "LT",
+            Equ(..) => "EQU",
+            Arb(..) => "ARB",
+            Hlt => "HLT",
+        }
+    }
+
+    /// Number of memory cells taken by the instruction, parameters included.
+    pub fn size(&self) -> usize {
+        match self {
+            Add(..) | Mul(..) | Lt(..) | Equ(..) => 4,
+            Jnz(..) | Jz(..) => 3,
+            Inp(..) | Out(..) | Arb(..) => 2,
+            Hlt => 1,
+        }
+    }
+}
+
+impl<W: Word> fmt::Display for Opcode<Parameter<W>> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let m = self.mnemonic();
+        match self {
+            Add(a, b, c) | Mul(a, b, c) | Lt(a, b, c) | Equ(a, b, c) => {
+                write!(f, "{} {}, {} -> {}", m, a, b, c)
+            }
+            Inp(a) => write!(f, "{} -> {}", m, a),
+            Out(a) | Arb(a) => write!(f, "{} {}", m, a),
+            Jnz(a, b) | Jz(a, b) => write!(f, "{} {}, {}", m, a, b),
+            Hlt => write!(f, "{}", m),
+        }
+    }
+}
+
 impl TryFrom<i32> for Opcode<Parameter<()>> {
-    type Error = String;
+    type Error = DecodeError;
 
     fn try_from(x: i32) -> Result<Opcode<Parameter<()>>, Self::Error> {
         let p = |i| digit(x / 100, i).try_into();
@@ -106,287 +189,858 @@ impl TryFrom<i32> for Opcode<Parameter<()>> {
         let op = match x % 100 {
             1 => Add(a, b, c),
             2 => Mul(a, b, c),
-            3 => Inp(c),
+            3 => Inp(a),
             4 => Out(a),
             5 => Jnz(a, b),
             6 => Jz(a, b),
             7 => Lt(a, b, c),
             8 => Equ(a, b, c),
+            9 => Arb(a),
             99 => Hlt,
-            o => Err(format!("Unknown operation {:?}", o).to_string())?,
+            o => Err(DecodeError::UnknownOpcode(o))?,
         };
         Ok(op)
     }
 }
 
 #[derive(Debug, Clone)]
-pub struct Process {
+pub struct Process<W = i64> {
     pc: usize,
-    intcode: Vec<i32>,
-    status: ProcessStatus,
+    rb: W,
+    memory: Memory<W>,
+    status: ProcessStatus<W>,
+    /// Queued inputs, consumed by `Inp` instructions before asking the caller.
+    inputs: VecDeque<W>,
+    /// Outputs collected by `run_until_blocked`.
+    output: Vec<W>,
+    /// Instructions left to run, if limited.
+    budget: Option<u64>,
+    /// State saved at a backward jump since the last I/O, when looking for
+    /// infinite loops.
+    loops: Option<LoopCheck<W>>,
+    /// Whether instructions are cached, see `without_cache`.
+    caching: bool,
+    /// Decoded instructions, empty until the program first jumps back.
+    cache: Cache<W>,
+    /// Undo log for stepping backwards, see `with_history`.
+    history: Option<history::History<W>>,
+}
+
+type Instruction<W> = Opcode<Parameter<W>>;
+
+/// Brent's cycle detection over the states at backward jumps: one state is
+/// kept and compared in full with every later one, and replaced after twice
+/// as many jumps each time. A loop is found within a few of its turns
+/// without remembering every state along the way.
+#[derive(Debug, Clone)]
+struct LoopCheck<W> {
+    saved: Option<(usize, W, Memory<W>)>,
+    /// Backward jumps since `saved` was taken.
+    jumps: u64,
+    /// Jumps to go before replacing `saved`.
+    period: u64,
+}
+
+impl<W> LoopCheck<W> {
+    fn new() -> Self {
+        LoopCheck {
+            saved: None,
+            jumps: 0,
+            period: 1,
+        }
+    }
+}
+
+/// Addresses below this get a slot in the instruction cache.
+const CACHE_LIMIT: usize = 1 << 16;
+
+/// Decoded instructions by address, dropped when their cells are written.
+#[derive(Debug, Clone)]
+struct Cache<W> {
+    instructions: Vec<Option<Instruction<W>>>,
+    /// Whether each cell is part of a cached instruction, so that writing
+    /// data only costs a lookup.
+    code: Vec<bool>,
+}
+
+impl<W: Copy> Cache<W> {
+    fn new() -> Self {
+        Cache {
+            instructions: vec![],
+            code: vec![],
+        }
+    }
+
+    fn get(&self, address: usize) -> Option<Instruction<W>> {
+        self.instructions.get(address).copied().flatten()
+    }
+
+    /// Caches `op` if it lies entirely within the cached addresses.
+    fn insert(&mut self, address: usize, op: Instruction<W>) {
+        let end = address + op.size();
+        if end <= self.code.len() {
+            self.instructions[address] = Some(op);
+            self.code[address..end].iter_mut().for_each(|c| *c = true);
+        }
+    }
+
+    /// Drops the instructions covering `address`, if any.
+    fn write(&mut self, address: usize) {
+        if self.code.get(address) == Some(&true) {
+            self.code[address] = false;
+            // instructions are at most 4 cells long
+            let start = address.saturating_sub(3);
+            self.instructions[start..=address]
+                .iter_mut()
+                .for_each(|op| *op = None);
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum ProcessStatus {
+pub enum ProcessStatus<W = i64> {
     Paused,
-    Outputting(i32),
+    Outputting(W),
     Awaiting(Parameter<usize>),
     Exit,
+    Faulted(IntcodeError<W>),
+    /// The instruction budget ran out.
+    Exhausted,
+    /// The jump at this address closed a loop that repeats the same state
+    /// without any I/O, so the program can never finish.
+    Looping(usize),
 }
 use ProcessStatus::*;
 
-impl ProcessStatus {}
+impl<W: Word> Process<W> {
+    pub fn new(code: Intcode<W>) -> Self {
+        Process::with_memory(Memory::dense(code.into()))
+    }
+
+    /// Process whose memory is a map, for programs writing to very high addresses.
+    pub fn sparse(code: Intcode<W>) -> Self {
+        Process::with_memory(Memory::sparse(code.into()))
+    }
 
-impl Process {
-    pub fn new(code: Intcode) -> Self {
-        let v: Vec<i32> = code.into();
+    pub fn with_memory(memory: Memory<W>) -> Self {
         Process {
             pc: 0,
-            intcode: v,
+            rb: W::from(0),
+            memory,
             status: Paused,
+            inputs: VecDeque::new(),
+            output: vec![],
+            budget: None,
+            loops: None,
+            caching: true,
+            cache: Cache::new(),
+            history: None,
+        }
+    }
+
+    /// Queues `inputs` for the program, see `push_input`.
+    pub fn with_inputs<I: IntoIterator<Item = W>>(mut self, inputs: I) -> Self {
+        inputs.into_iter().for_each(|x| self.push_input(x));
+        self
+    }
+
+    /// Stops the process with `Exhausted` after running `budget` instructions.
+    pub fn with_budget(mut self, budget: u64) -> Self {
+        self.budget = Some(budget);
+        self
+    }
+
+    /// Stops the process with `Looping` when it gets stuck in a loop without
+    /// I/O. The machine state at backward jumps is compared with one saved
+    /// earlier, which is replaced less and less often.
+    pub fn with_loop_detection(mut self) -> Self {
+        self.loops = Some(LoopCheck::new());
+        self
+    }
+
+    /// Decodes every instruction as it is run, instead of only the first
+    /// time. Mostly useful to measure what the cache saves.
+    pub fn without_cache(mut self) -> Self {
+        self.caching = false;
+        self.cache = Cache::new();
+        self
+    }
+
+    pub fn budget(&self) -> Option<u64> {
+        self.budget
+    }
+
+    /// Changes the instruction budget, letting an exhausted process resume.
+    pub fn set_budget(&mut self, budget: Option<u64>) {
+        self.budget = budget;
+        if self.status == Exhausted {
+            self.status = Paused;
         }
     }
 
-    fn set(&mut self, param: &Parameter<usize>, value: i32) -> Result<(), String> {
-        match param {
-            Pos(x) => Ok(self.intcode[*x] = value),
-            Imm(_) => Err("Can't set to immediate value.".to_owned()),
+    pub fn with_memory_limit(mut self, limit: usize) -> Self {
+        self.memory = self.memory.with_limit(limit);
+        self
+    }
+
+    fn fault(&self, err: impl FnOnce(usize, W) -> IntcodeError<W>) -> IntcodeError<W> {
+        err(self.pc, self.memory.get(self.pc))
+    }
+
+    /// Checks `address` is a valid cell, i.e. not negative and below the
+    /// memory limit.
+    fn to_address(&self, address: W) -> Result<usize, IntcodeError<W>> {
+        if address < W::from(0) {
+            return Err(self.fault(|pc, instruction| NegativeAddress {
+                pc,
+                instruction,
+                address,
+            }));
+        }
+        let limit = self.memory.limit();
+        match address.try_into() {
+            Ok(x) if x < limit => Ok(x),
+            _ => Err(self.fault(|pc, instruction| OutOfBounds {
+                pc,
+                instruction,
+                address,
+                limit,
+            })),
         }
     }
 
-    fn try_set(&mut self, param: &Parameter<i32>, value: i32) -> Result<(), String> {
+    /// Faults if an arithmetic result overflowed.
+    fn checked(&self, result: Option<W>) -> Result<W, IntcodeError<W>> {
+        result.ok_or_else(|| self.fault(|pc, instruction| Overflow { pc, instruction }))
+    }
+
+    fn set(&mut self, x: usize, value: W) {
+        if self.history.is_some() {
+            self.record_write(x);
+        }
+        self.write(x, value);
+    }
+
+    /// Writes a cell, without recording it in the history.
+    fn write(&mut self, x: usize, value: W) {
+        self.memory
+            .set(x, value)
+            .expect("addresses are checked by to_address");
+        self.cache.write(x);
+    }
+
+    /// Cell a parameter of the current instruction points at.
+    pub fn address(&self, param: &Parameter<W>) -> Result<usize, IntcodeError<W>> {
         match *param {
-            Imm(_) => Err("Can't set to immediate value.".to_owned()),
-            Pos(p) => {
-                let x: usize = p
-                    .try_into()
-                    .map_err(|_| format!("Invalid index from {:?}", param).to_owned())?;
-                Ok(self.intcode[x] = value)
-            }
+            Imm(_) => Err(self.fault(|pc, instruction| WriteToImmediate { pc, instruction })),
+            Pos(p) => self.to_address(p),
+            Rel(p) => self.to_address(self.checked(self.rb.checked_add(p))?),
         }
     }
 
-    fn get(&self, param: &Parameter<i32>) -> Result<i32, String> {
+    fn try_set(&mut self, param: &Parameter<W>, value: W) -> Result<(), IntcodeError<W>> {
+        let x = self.address(param)?;
+        self.set(x, value);
+        Ok(())
+    }
+
+    /// Value of a parameter of the current instruction.
+    pub fn get(&self, param: &Parameter<W>) -> Result<W, IntcodeError<W>> {
         match *param {
             Imm(x) => Ok(x),
-            Pos(p) => {
-                let x: usize = p
-                    .try_into()
-                    .map_err(|_| format!("Invalid index from {:?}", param).to_owned())?;
-                Ok(self.intcode[x])
-            }
+            _ => Ok(self.memory.get(self.address(param)?)),
         }
     }
 
-    fn jmp(&mut self, pos: usize) {
+    fn target(&self, param: &Parameter<W>) -> Result<usize, IntcodeError<W>> {
+        self.to_address(self.get(param)?)
+    }
+
+    fn jmp(&mut self, pos: usize) -> Option<Evaluation<W>> {
+        if pos <= self.pc {
+            if self.revisits_state() {
+                return Some(Cycle(self.pc));
+            }
+            self.warm_cache();
+        }
         self.pc = pos;
+        None
+    }
+
+    /// Compares the current state with the saved one, telling whether the
+    /// program came back to it.
+    fn revisits_state(&mut self) -> bool {
+        let check = match &mut self.loops {
+            Some(check) => check,
+            None => return false,
+        };
+        if let Some((pc, rb, memory)) = &check.saved {
+            if *pc == self.pc && *rb == self.rb && *memory == self.memory {
+                return true;
+            }
+        }
+        check.jumps += 1;
+        if check.jumps == check.period {
+            check.saved = Some((self.pc, self.rb, self.memory.clone()));
+            check.jumps = 0;
+            check.period *= 2;
+        }
+        false
+    }
+
+    fn forget_states(&mut self) {
+        if let Some(check) = &mut self.loops {
+            *check = LoopCheck::new();
+        }
     }
 
     fn inc(&mut self, steps: usize) {
         self.pc += steps;
     }
 
-    fn inc_setter<'a>(&'a self) -> Box<dyn FnMut(&Parameter<()>) -> Parameter<i32> + 'a> {
-        let mut i = 0;
-        Box::new(move |m| {
+    fn populate(&self, code: Opcode<Parameter<()>>) -> Instruction<W> {
+        let mut i = self.pc;
+        code.mut_map(&mut |m: &Parameter<()>| {
             i += 1;
-            match m {
-                Pos(_) => Pos(self.intcode[self.pc + i]),
-                Imm(_) => Imm(self.intcode[self.pc + i]),
-            }
+            m.map(|_| self.memory.get(i))
         })
     }
 
-    fn populate(&self, code: Opcode<Parameter<()>>) -> Opcode<Parameter<i32>> {
-        code.mut_map(&mut self.inc_setter())
+    /// Decodes the instruction at the pc.
+    pub fn current(&self) -> Result<Instruction<W>, IntcodeError<W>> {
+        match self.cache.get(self.pc) {
+            Some(op) => Ok(op),
+            None => self.decode(),
+        }
     }
 
-    fn current(&self) -> Result<Opcode<Parameter<i32>>, String> {
-        let code: Opcode<Parameter<()>> = self.intcode[self.pc].try_into()?;
+    /// Like `current`, caching the instruction.
+    fn fetch(&mut self) -> Result<Instruction<W>, IntcodeError<W>> {
+        if let Some(op) = self.cache.get(self.pc) {
+            return Ok(op);
+        }
+        let op = self.decode()?;
+        self.cache.insert(self.pc, op);
+        Ok(op)
+    }
+
+    /// Sets up the cache the first time the program jumps back, so code
+    /// that only runs once isn't cached. Only the low addresses, where code
+    /// is, get an entry.
+    fn warm_cache(&mut self) {
+        if self.caching && self.cache.code.is_empty() {
+            let len = self.memory.len().min(CACHE_LIMIT);
+            self.cache.instructions.resize(len, None);
+            self.cache.code.resize(len, false);
+        }
+    }
+
+    fn decode(&self) -> Result<Instruction<W>, IntcodeError<W>> {
+        let raw = self.memory.get(self.pc);
+        let word: i32 = raw
+            .try_into()
+            .map_err(|_| self.fault(|pc, instruction| UnknownOpcode { pc, instruction }))?;
+        let code: Opcode<Parameter<()>> = word
+            .try_into()
+            .map_err(|e| IntcodeError::decode(self.pc, raw, e))?;
         let op = self.populate(code);
         Ok(op)
     }
 
-    fn eval(&mut self) -> Evaluation {
-        match self.eval_inner() {
-            Ok(ev) => ev,
-            Err(s) => EvaluationError(s),
+    /// Runs instructions until one yields control back to the caller.
+    fn eval(&mut self) -> Evaluation<W> {
+        loop {
+            match self.exec() {
+                Ok(None) => continue,
+                Ok(Some(ev)) => return ev,
+                Err(e) => return Fault(e),
+            }
         }
     }
 
-    pub fn resume(&mut self) -> ProcessStatus {
-        let ev = match self.status {
-            Paused | Outputting(_) => self.eval(),
-            // Do nothing if not paused
-            _ => return self.status,
-        };
+    fn settle(&mut self, ev: Evaluation<W>) -> ProcessStatus<W> {
         match ev {
             Input(dest) => self.status = Awaiting(dest),
             Output(o) => self.status = Outputting(o),
-            EvaluationError(s) => {
-                println!("{}", s);
-                self.status = Exit
-            }
+            Fault(e) => self.status = Faulted(e),
             Halt => self.status = Exit,
+            OutOfBudget => self.status = Exhausted,
+            Cycle(pc) => self.status = Looping(pc),
         };
         self.status
     }
 
-    pub fn feed(&mut self, input: i32) -> ProcessStatus {
+    pub fn resume(&mut self) -> ProcessStatus<W> {
+        let ev = match self.status {
+            Paused | Outputting(_) => self.eval(),
+            // Do nothing if not paused
+            _ => return self.status,
+        };
+        self.settle(ev)
+    }
+
+    /// Executes a single instruction. The process stays `Paused` unless the
+    /// instruction yields.
+    pub fn step(&mut self) -> ProcessStatus<W> {
+        let ev = match self.status {
+            Paused | Outputting(_) => match self.exec() {
+                Ok(None) => {
+                    self.status = Paused;
+                    return self.status;
+                }
+                Ok(Some(ev)) => ev,
+                Err(e) => Fault(e),
+            },
+            _ => return self.status,
+        };
+        self.settle(ev)
+    }
+
+    /// Queues an input for the next `Inp` instructions. A process already
+    /// awaiting input is fed right away.
+    pub fn push_input(&mut self, input: W) {
+        match self.status {
+            Awaiting(_) if self.inputs.is_empty() => {
+                self.feed(input).expect("process is awaiting input");
+            }
+            _ => self.inputs.push_back(input),
+        }
+    }
+
+    /// Runs until the program needs input that isn't queued, halts or stops
+    /// for any other reason, collecting its outputs.
+    pub fn run_until_blocked(&mut self) -> ProcessStatus<W> {
+        loop {
+            match self.resume() {
+                Outputting(x) => self.output.push(x),
+                s => return s,
+            }
+        }
+    }
+
+    /// Iterates over the outputs of the program. Iteration stops when it
+    /// halts or blocks, e.g. asking for input that isn't queued; `status`
+    /// tells which.
+    pub fn outputs(&mut self) -> Outputs<'_, W> {
+        Outputs { process: self }
+    }
+
+    /// Takes the outputs collected so far.
+    pub fn take_output(&mut self) -> Vec<W> {
+        std::mem::take(&mut self.output)
+    }
+
+    /// Writes `input` to the cell an `Inp` instruction is waiting on. Feeding
+    /// a process that is not awaiting input is an error.
+    pub fn feed(&mut self, input: W) -> Result<ProcessStatus<W>, IntcodeError<W>> {
         match self.status {
-            Awaiting(dest) => {
-                self.set(&dest, input).unwrap();
+            Awaiting(Pos(dest)) => {
+                if self.history.is_some() {
+                    self.checkpoint(self.status);
+                }
+                self.set(dest, input);
+                self.inc(2);
+                self.forget_states();
+                if self.history.is_some() {
+                    self.commit(true);
+                }
                 self.status = Paused;
-                self.status
+                Ok(self.status)
             }
-            _ => panic!(format!("Trying to feed {:?}", self.status)),
+            _ => Err(self.fault(|pc, instruction| NotAwaiting { pc, instruction })),
         }
     }
 
-    pub fn head(&self) -> i32 {
-        self.intcode[0]
+    pub fn pc(&self) -> usize {
+        self.pc
+    }
+
+    pub fn relative_base(&self) -> W {
+        self.rb
+    }
+
+    pub fn memory(&self) -> &Memory<W> {
+        &self.memory
+    }
+
+    pub fn head(&self) -> W {
+        self.memory.get(0)
+    }
+
+    /// Executes the instruction at the pc, returning an evaluation if it
+    /// needs the caller (I/O or halt).
+    fn exec(&mut self) -> Result<Option<Evaluation<W>>, IntcodeError<W>> {
+        if self.history.is_some() {
+            return self.exec_with_history();
+        }
+        self.exec_inner()
     }
 
-    fn eval_inner(&mut self) -> Result<Evaluation, String> {
-        let curr = self.current()?;
+    /// `exec` for a process with history, kept out of line so that the
+    /// path without it stays small enough to be inlined.
+    #[inline(never)]
+    fn exec_with_history(&mut self) -> Result<Option<Evaluation<W>>, IntcodeError<W>> {
+        self.checkpoint(Paused);
+        let result = self.exec_inner();
+        // an instruction that faults, waits for input, is out of budget or
+        // closes a loop doesn't run, so there is nothing to undo
+        let ran = matches!(result, Ok(None) | Ok(Some(Output(_))) | Ok(Some(Halt)));
+        self.commit(ran);
+        result
+    }
+
+    fn exec_inner(&mut self) -> Result<Option<Evaluation<W>>, IntcodeError<W>> {
+        if let Some(budget) = &mut self.budget {
+            if *budget == 0 {
+                return Ok(Some(OutOfBudget));
+            }
+            *budget -= 1;
+        }
+        let curr = self.fetch()?;
         let ev = match curr {
             Add(a, b, c) => {
-                self.try_set(&c, self.get(&a)? + self.get(&b)?)?;
+                let sum = self.get(&a)?.checked_add(self.get(&b)?);
+                self.try_set(&c, self.checked(sum)?)?;
                 self.inc(4);
-                self.eval()
+                None
             }
             Mul(a, b, c) => {
                 let p1 = self.get(&a)?;
                 let p2 = self.get(&b)?;
-                self.try_set(&c, p1 * p2)?;
+                self.try_set(&c, self.checked(p1.checked_mul(p2))?)?;
                 self.inc(4);
-                self.eval()
+                None
             }
             Out(a) => {
+                let out = self.get(&a)?;
                 self.inc(2);
-                Output(self.get(&a)?)
+                self.forget_states();
+                Some(Output(out))
             }
-            // ugly af
             Inp(a) => {
-                self.inc(2);
-                Input(a.try_map(|&x| x.try_into()).unwrap())
+                let dest = self.address(&a)?;
+                match self.inputs.pop_front() {
+                    Some(x) => {
+                        if self.history.is_some() {
+                            self.record_input(x);
+                        }
+                        self.set(dest, x);
+                        self.inc(2);
+                        self.forget_states();
+                        None
+                    }
+                    // the pc stays on the instruction until it is fed
+                    None => Some(Input(Pos(dest))),
+                }
             }
             Jnz(a, b) => {
-                if self.get(&a)? != 0 {
-                    self.jmp(self.get(&b)?.try_into().unwrap()); // TODO fix this
+                if self.get(&a)? != W::from(0) {
+                    let target = self.target(&b)?;
+                    self.jmp(target)
                 } else {
                     self.inc(3);
+                    None
                 }
-                self.eval()
             }
             Jz(a, b) => {
-                if self.get(&a)? == 0 {
-                    self.jmp(self.get(&b)?.try_into().unwrap()); // TODO fix this
+                if self.get(&a)? == W::from(0) {
+                    let target = self.target(&b)?;
+                    self.jmp(target)
                 } else {
                     self.inc(3);
+                    None
                 }
-                self.eval()
             }
             Lt(a, b, c) => {
-                self.try_set(&c, if self.get(&a) < self.get(&b) { 1 } else { 0 })?;
+                let lt = self.get(&a)? < self.get(&b)?;
+                self.try_set(&c, W::from(if lt { 1 } else { 0 }))?;
                 self.inc(4);
-                self.eval()
+                None
             }
             Equ(a, b, c) => {
-                self.try_set(&c, if self.get(&a) == self.get(&b) { 1 } else { 0 })?;
+                let eq = self.get(&a)? == self.get(&b)?;
+                self.try_set(&c, W::from(if eq { 1 } else { 0 }))?;
                 self.inc(4);
-                self.eval()
+                None
             }
-            Hlt => Halt,
+            Arb(a) => {
+                self.rb = self.checked(self.rb.checked_add(self.get(&a)?))?;
+                self.inc(2);
+                None
+            }
+            Hlt => Some(Halt),
         };
         Ok(ev)
     }
 
-    pub fn status(&self) -> ProcessStatus {
+    pub fn status(&self) -> ProcessStatus<W> {
         self.status
     }
 }
 
+/// Calls `step` until the process yields, as `Process::resume` would, for
+/// wrappers that look at every instruction. Such wrappers step a `Process`
+/// from the outside, so processes that aren't wrapped don't pay for them.
+/// `status` is the status of the process before the first step.
+fn resume_by_steps<W: Word, F>(status: ProcessStatus<W>, mut step: F) -> ProcessStatus<W>
+where
+    F: FnMut() -> ProcessStatus<W>,
+{
+    match status {
+        Paused | Outputting(_) => loop {
+            match step() {
+                Paused => continue,
+                s => return s,
+            }
+        },
+        s => s,
+    }
+}
+
+/// Iterator over the outputs of a process, see `Process::outputs`.
+pub struct Outputs<'a, W> {
+    process: &'a mut Process<W>,
+}
+
+impl<'a, W: Word> Iterator for Outputs<'a, W> {
+    type Item = W;
+
+    fn next(&mut self) -> Option<W> {
+        match self.process.resume() {
+            Outputting(x) => Some(x),
+            _ => None,
+        }
+    }
+}
+
 use Evaluation::*;
-pub enum Evaluation {
+pub enum Evaluation<W> {
     Input(Parameter<usize>),
-    Output(i32),
+    Output(W),
     Halt,
-    EvaluationError(String),
+    Fault(IntcodeError<W>),
+    OutOfBudget,
+    Cycle(usize),
 }
 
-impl<'a> fmt::Debug for Evaluation {
+impl<W: Word> fmt::Debug for Evaluation<W> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Input(dest) => write!(f, "Input({:?})", dest),
             Output(o) => write!(f, "Output({})", o),
             Halt => write!(f, "Halt"),
-            EvaluationError(err) => write!(f, "EvaluationError {}", err),
-        }
-    }
-}
-
-pub trait Runnable {
-    fn run(&mut self, input: i32) -> Option<i32>;
-}
-
-// #[derive(Debug, Clone)]
-// pub struct ProcessChain(Vec<Process>);
-
-// use std::slice::IterMut;
-
-// impl ProcessChain {
-//     pub fn iter_mut(&mut self) -> IterMut<'_, Process> {
-//         self.0.iter_mut()
-//     }
-
-//     pub fn cycle(&mut self, init: i32) -> Option<i32> {
-//         let mut outputs: Vec<Option<i32>> = vec![None; self.0.len()];
-//         let mut res: i32 = init;
-//         let mut cont = true;
-//         while cont {
-//             cont = false;
-//             for (i, p) in self.0.iter_mut().enumerate() {
-//                 match p.feed(res) {
-//                     Outputting(x) => {
-//                         res = x;
-//                         outputs[i] = Some(x);
-//                         cont = true
-//                     }
-//                     _ => {
-//                         res = outputs[i]?;
-//                     }
-//                 }
-//             }
-//         }
-//         Some(res)
-//     }
-// }
-
-// use std::iter::FromIterator;
-
-// impl<'a> FromIterator<Process> for ProcessChain {
-//     fn from_iter<T>(iter: T) -> Self
-//     where
-//         T: IntoIterator<Item = Process>,
-//     {
-//         ProcessChain(iter.into_iter().collect())
-//     }
-// }
-
-// impl IntoIterator for ProcessChain {
-//     type Item = Process;
-//     type IntoIter = std::vec::IntoIter<Process>;
-
-//     fn into_iter(self) -> Self::IntoIter {
-//         self.0.into_iter()
-//     }
-// }
-
-// impl Runnable for ProcessChain {
-//     fn run(&mut self, input: i32) -> Option<i32> {
-//         let mut it = self.iter_mut();
-//         let res = it.next()?.feed(input).out()?;
-//         it.try_fold(res, |r, p| p.feed(r).out())
-//     }
-// }
+            Fault(err) => write!(f, "Fault({})", err),
+            OutOfBudget => write!(f, "OutOfBudget"),
+            Cycle(pc) => write!(f, "Cycle({})", pc),
+        }
+    }
+}
+
+/// A machine that turns inputs into outputs, such as a process or a chain of
+/// processes, so solutions can be written against any of them.
+pub trait Runnable<W = i64> {
+    /// Gives `inputs` to the machine and runs it until it blocks, returning
+    /// the outputs it produced.
+    fn run(&mut self, inputs: &[W]) -> Vec<W>;
+
+    fn status(&self) -> ProcessStatus<W>;
+
+    /// Whether the machine is blocked waiting for more input.
+    fn awaiting(&self) -> bool {
+        matches!(self.status(), Awaiting(_))
+    }
+}
+
+impl<W: Word> Runnable<W> for Process<W> {
+    fn run(&mut self, inputs: &[W]) -> Vec<W> {
+        inputs.iter().for_each(|&x| self.push_input(x));
+        self.run_until_blocked();
+        self.take_output()
+    }
+
+    fn status(&self) -> ProcessStatus<W> {
+        self.status
+    }
+}
+
+#[test]
+pub fn relative_mode_test() {
+    let code: Intcode = "109,3,203,4,204,4,99,0".parse().unwrap();
+    let mut p = Process::new(code);
+    assert_eq!(p.resume(), Awaiting(Pos(7)));
+    p.feed(42).unwrap();
+    assert_eq!(p.resume(), Outputting(42));
+    assert_eq!(p.resume(), Exit);
+}
+
+#[test]
+pub fn word_size_test() {
+    let code: Intcode = "1102,34915192,34915192,7,4,7,99,0".parse().unwrap();
+    assert_eq!(Process::new(code).resume(), Outputting(1219070632396864));
+
+    let code: Intcode<i128> = "1102,4611686018427387904,4,5,104,0,99".parse().unwrap();
+    let mut p = Process::new(code);
+    assert_eq!(p.resume(), Outputting(1 << 64));
+}
+
+#[test]
+pub fn growing_memory_test() {
+    let quine = "109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99";
+    let code: Intcode = quine.parse().unwrap();
+    let mut p = Process::new(code.clone());
+    let mut out = vec![];
+    while let Outputting(x) = p.resume() {
+        out.push(x);
+    }
+    assert_eq!(Intcode(out), code);
+
+    let code: Intcode = "1101,1,1,1000000000000,99".parse().unwrap();
+    assert_eq!(Process::sparse(code.clone()).resume(), Exit);
+    let mut p = Process::new(code).with_memory_limit(100);
+    let err = OutOfBounds {
+        pc: 0,
+        instruction: 1101,
+        address: 1000000000000,
+        limit: 100,
+    };
+    assert_eq!(p.resume(), Faulted(err));
+}
+
+#[test]
+pub fn fault_test() {
+    let code: Intcode = "1,0,0,-1,99".parse().unwrap();
+    let err = NegativeAddress {
+        pc: 0,
+        instruction: 1,
+        address: -1,
+    };
+    assert_eq!(Process::new(code).resume(), Faulted(err));
+
+    let code: Intcode = "1101,1,1,5,42,0".parse().unwrap();
+    let mut p = Process::new(code);
+    let err = UnknownOpcode {
+        pc: 4,
+        instruction: 42,
+    };
+    assert_eq!(p.resume(), Faulted(err));
+    assert!(p.feed(1).is_err());
+
+    let overflow = |pc, instruction| Faulted(Overflow { pc, instruction });
+    let code: Intcode = "109,9223372036854775807,204,1,99".parse().unwrap();
+    assert_eq!(Process::new(code).resume(), overflow(2, 204));
+    let code: Intcode = "109,9223372036854775807,109,1,99".parse().unwrap();
+    assert_eq!(Process::new(code).resume(), overflow(2, 109));
+    let code: Intcode = "1102,4294967296,4294967296,0,99".parse().unwrap();
+    assert_eq!(Process::new(code).resume(), overflow(0, 1102));
+    let code: Intcode<i32> = "1101,2147483647,1,0,99".parse().unwrap();
+    assert_eq!(
+        Process::new(code).resume(),
+        Faulted(Overflow {
+            pc: 0,
+            instruction: 1101
+        })
+    );
+}
+
+#[test]
+pub fn long_loop_test() {
+    // counts [10] down from two million without any I/O in between
+    let code: Intcode = "1001,10,-1,10,1005,10,0,4,10,99,2000000".parse().unwrap();
+    let mut p = Process::new(code);
+    assert_eq!(p.resume(), Outputting(0));
+    assert_eq!(p.resume(), Exit);
+}
+
+#[test]
+pub fn budget_test() {
+    let code: Intcode = "1001,10,-1,10,1005,10,0,4,10,99,2000000".parse().unwrap();
+    let mut p = Process::new(code).with_budget(1000);
+    assert_eq!(p.resume(), Exhausted);
+    assert_eq!(p.resume(), Exhausted);
+    p.set_budget(None);
+    assert_eq!(p.resume(), Outputting(0));
+}
+
+#[test]
+pub fn loop_detection_test() {
+    // prints [9] once, then spins on the jump at 5
+    let code: Intcode = "4,9,1105,1,5,1105,1,5,99,3".parse().unwrap();
+    let mut p = Process::new(code).with_loop_detection();
+    assert_eq!(p.resume(), Outputting(3));
+    assert_eq!(p.resume(), Looping(5));
+
+    // flips [7] between 0 and 1 forever, so states repeat every other turn
+    let code: Intcode = "1008,7,0,7,1105,1,0,0".parse().unwrap();
+    let mut p = Process::new(code).with_loop_detection();
+    assert_eq!(p.resume(), Looping(4));
+
+    let code: Intcode = "1001,10,-1,10,1005,10,0,4,10,99,2000".parse().unwrap();
+    let mut p = Process::new(code).with_loop_detection();
+    assert_eq!(p.resume(), Outputting(0));
+}
+
+#[test]
+pub fn input_queue_test() {
+    // adds up pairs of inputs until a pair adds up to zero
+    let code: Intcode = "3,14,3,15,1,14,15,16,4,16,1005,16,0,99,0,0,0"
+        .parse()
+        .unwrap();
+    let mut p = Process::new(code);
+    p.push_input(1);
+    p.push_input(2);
+    p.push_input(3);
+    assert_eq!(p.run_until_blocked(), Awaiting(Pos(15)));
+    assert_eq!(p.take_output(), vec![3]);
+    p.push_input(4);
+    p.push_input(0);
+    p.push_input(0);
+    assert_eq!(p.run_until_blocked(), Exit);
+    assert_eq!(p.take_output(), vec![7, 0]);
+}
+
+#[test]
+pub fn outputs_test() {
+    // echoes its inputs until it reads a zero
+    let code: Intcode = "3,9,4,9,1005,9,0,99,0,0".parse().unwrap();
+    let mut p = Process::new(code).with_inputs(vec![3, 2, 1]);
+    assert_eq!(p.outputs().collect::<Vec<_>>(), vec![3, 2, 1]);
+    assert_eq!(p.status(), Awaiting(Pos(9)));
+    p.push_input(0);
+    assert_eq!(p.outputs().last(), Some(0));
+    assert_eq!(p.status(), Exit);
+}
+
+#[test]
+pub fn runnable_test() {
+    fn sum<R: Runnable>(machine: &mut R, xs: &[i64]) -> Vec<i64> {
+        machine.run(xs)
+    }
+    let code = conformance::running_total();
+    let mut p = Process::new(code);
+    assert_eq!(sum(&mut p, &[1, 2, 3]), vec![1, 3, 6]);
+    assert!(p.awaiting());
+    assert_eq!(sum(&mut p, &[4]), vec![10]);
+}
+
+#[test]
+pub fn self_modifying_test() {
+    // overwrites its own ADD with a MUL before running it again
+    let code: Intcode = "1101,2,3,13,4,13,1101,0,1102,0,1105,1,0,0".parse().unwrap();
+    let mut p = Process::new(code.clone());
+    assert_eq!(p.resume(), Outputting(5));
+    assert_eq!(p.resume(), Outputting(6));
+    let mut p = Process::new(code).without_cache();
+    assert_eq!(p.resume(), Outputting(5));
+    assert_eq!(p.resume(), Outputting(6));
+
+    // bumps the operand of an instruction that is already cached
+    let code: Intcode = "104,7,1001,1,1,1,1105,1,0".parse().unwrap();
+    let outputs: Vec<i64> = Process::new(code).outputs().take(3).collect();
+    assert_eq!(outputs, vec![7, 8, 9]);
+
+    // loops at an address far beyond what the cache covers
+    let code: Intcode = "1101,99,0,1000000000000,1105,1,1000000000000"
+        .parse()
+        .unwrap();
+    assert_eq!(Process::sparse(code).resume(), Exit);
+}
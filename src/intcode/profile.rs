@@ -0,0 +1,170 @@
+//! Counting how often each instruction runs, to find where a program spends
+//! its time.
+
+use super::Opcode::*;
+use super::ProcessStatus::*;
+use super::{resume_by_steps, IntcodeError, Process, ProcessStatus, Word};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Execution counts gathered by a `Profiler`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Profile {
+    pub total: u64,
+    pub by_address: BTreeMap<usize, u64>,
+    pub by_mnemonic: BTreeMap<&'static str, u64>,
+    /// Backward jumps taken, by target and jump address: a loop over
+    /// `target..=jump`.
+    pub loops: BTreeMap<(usize, usize), u64>,
+}
+
+impl Profile {
+    fn count(&mut self, address: usize, mnemonic: &'static str) {
+        self.total += 1;
+        *self.by_address.entry(address).or_insert(0) += 1;
+        *self.by_mnemonic.entry(mnemonic).or_insert(0) += 1;
+    }
+
+    /// The `n` most executed addresses, hottest first.
+    pub fn hottest(&self, n: usize) -> Vec<(usize, u64)> {
+        let mut counts: Vec<_> = self.by_address.iter().map(|(&a, &c)| (a, c)).collect();
+        counts.sort_by(|x, y| y.1.cmp(&x.1).then(x.0.cmp(&y.0)));
+        counts.truncate(n);
+        counts
+    }
+
+    /// The `n` loops iterated the most, as `(target, jump, iterations)`.
+    pub fn hot_loops(&self, n: usize) -> Vec<(usize, usize, u64)> {
+        let mut loops: Vec<_> = self.loops.iter().map(|(&(t, j), &c)| (t, j, c)).collect();
+        loops.sort_by(|x, y| y.2.cmp(&x.2).then((x.0, x.1).cmp(&(y.0, y.1))));
+        loops.truncate(n);
+        loops
+    }
+
+    fn share(&self, count: u64) -> f64 {
+        100.0 * count as f64 / self.total.max(1) as f64
+    }
+}
+
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} instructions", self.total)?;
+        let mut kinds: Vec<_> = self.by_mnemonic.iter().collect();
+        kinds.sort_by(|x, y| y.1.cmp(x.1).then(x.0.cmp(y.0)));
+        writeln!(f, "by instruction:")?;
+        for (mnemonic, &count) in kinds {
+            writeln!(
+                f,
+                "  {:<4}{:>12} {:>6.2}%",
+                mnemonic,
+                count,
+                self.share(count)
+            )?;
+        }
+        writeln!(f, "hottest addresses:")?;
+        for (address, count) in self.hottest(10) {
+            writeln!(
+                f,
+                "  {:04}{:>12} {:>6.2}%",
+                address,
+                count,
+                self.share(count)
+            )?;
+        }
+        if !self.loops.is_empty() {
+            writeln!(f, "hottest loops:")?;
+            for (target, jump, count) in self.hot_loops(5) {
+                writeln!(f, "  {:04}..{:04}{:>12} iterations", target, jump, count)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A process that counts every instruction it runs.
+///
+/// `resume` and `feed` behave as on `Process`. An `Inp` is counted once it
+/// is fed. Faulting instructions aren't counted, nor are those the budget or
+/// the loop detector stops from running.
+#[derive(Debug, Clone)]
+pub struct Profiler<W = i64> {
+    process: Process<W>,
+    profile: Profile,
+}
+
+impl<W: Word> Profiler<W> {
+    pub fn new(process: Process<W>) -> Self {
+        Profiler {
+            process,
+            profile: Profile::default(),
+        }
+    }
+
+    pub fn process(&self) -> &Process<W> {
+        &self.process
+    }
+
+    pub fn profile(&self) -> &Profile {
+        &self.profile
+    }
+
+    /// Executes one instruction and counts it.
+    pub fn step(&mut self) -> ProcessStatus<W> {
+        let pc = self.process.pc();
+        let op = self.process.current().ok();
+        let status = self.process.step();
+        match (op, status) {
+            (_, Awaiting(_)) | (_, Faulted(_)) | (_, Exhausted) | (_, Looping(_)) | (None, _) => {}
+            (Some(op), _) => {
+                self.profile.count(pc, op.mnemonic());
+                let to = self.process.pc();
+                if matches!(op, Jnz(..) | Jz(..)) && to <= pc {
+                    *self.profile.loops.entry((to, pc)).or_insert(0) += 1;
+                }
+            }
+        }
+        status
+    }
+
+    pub fn resume(&mut self) -> ProcessStatus<W> {
+        resume_by_steps(self.process.status(), || self.step())
+    }
+
+    pub fn feed(&mut self, input: W) -> Result<ProcessStatus<W>, IntcodeError<W>> {
+        let pc = self.process.pc();
+        let status = self.process.feed(input)?;
+        self.profile.count(pc, Inp(()).mnemonic());
+        Ok(status)
+    }
+
+    pub fn finish(self) -> (Process<W>, Profile) {
+        (self.process, self.profile)
+    }
+}
+
+#[test]
+pub fn profile_test() {
+    use super::Intcode;
+    // counts down from its input
+    let code: Intcode = "3,10,1001,10,-1,10,1005,10,2,99,0".parse().unwrap();
+    let mut p = Profiler::new(Process::new(code));
+    assert!(matches!(p.resume(), Awaiting(_)));
+    p.feed(3).unwrap();
+    assert_eq!(p.resume(), Exit);
+    let (_, profile) = p.finish();
+    assert_eq!(profile.total, 8);
+    assert_eq!(profile.by_mnemonic["ADD"], 3);
+    assert_eq!(profile.by_mnemonic["INP"], 1);
+    assert_eq!(profile.hottest(2), vec![(2, 3), (6, 3)]);
+    assert_eq!(profile.hot_loops(1), vec![(2, 6, 2)]);
+    let report = profile.to_string();
+    assert!(report.starts_with("8 instructions\n"));
+    assert!(report.contains("  ADD            3  37.50%\n"));
+
+    // the jump closing the loop doesn't run the second time
+    let code: Intcode = "1105,1,0".parse().unwrap();
+    let mut p = Profiler::new(Process::new(code).with_loop_detection());
+    assert_eq!(p.resume(), Looping(0));
+    assert_eq!(p.profile().total, 1);
+    assert_eq!(p.profile().hot_loops(1), vec![(0, 0, 1)]);
+}
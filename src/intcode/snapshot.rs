@@ -0,0 +1,242 @@
+//! Saving and restoring processes. A snapshot is a small text file:
+//!
+//! ```text
+//! intcode-snapshot 1
+//! pc 2
+//! rb 0
+//! status awaiting 225
+//! memory dense 1048576
+//! data 0 3,225,1,225,6,6,1100
+//! ```
+//!
+//! `memory` gives the backing store and its limit, and each `data` line a run
+//! of consecutive cells starting at the given address. `budget`, `inputs`
+//! (queued inputs) and `output` (collected outputs) lines are only present
+//! when there is something to save.
+
+use super::IntcodeError::{self, *};
+use super::ProcessStatus::{self, *};
+use super::{Memory, Parameter, Process, Word};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+const HEADER: &str = "intcode-snapshot 1";
+
+fn invalid(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn status_to_string<W: Word>(status: &ProcessStatus<W>) -> String {
+    match status {
+        Paused => "paused".to_owned(),
+        Outputting(x) => format!("outputting {}", x),
+        // the destination is always resolved to an address
+        Awaiting(Parameter::Pos(a) | Parameter::Imm(a) | Parameter::Rel(a)) => {
+            format!("awaiting {}", a)
+        }
+        Exit => "exit".to_owned(),
+        Faulted(e) => format!("faulted {}", error_to_string(e)),
+        Exhausted => "exhausted".to_owned(),
+        Looping(pc) => format!("looping {}", pc),
+    }
+}
+
+fn error_to_string<W: Word>(e: &IntcodeError<W>) -> String {
+    let (pc, instruction) = (e.pc(), e.instruction());
+    match e {
+        UnknownOpcode { .. } => format!("unknown-opcode {} {}", pc, instruction),
+        UnknownMode { mode, .. } => format!("unknown-mode {} {} {}", pc, instruction, mode),
+        WriteToImmediate { .. } => format!("write-to-immediate {} {}", pc, instruction),
+        NegativeAddress { address, .. } => {
+            format!("negative-address {} {} {}", pc, instruction, address)
+        }
+        OutOfBounds { address, limit, .. } => {
+            format!("out-of-bounds {} {} {} {}", pc, instruction, address, limit)
+        }
+        NotAwaiting { .. } => format!("not-awaiting {} {}", pc, instruction),
+        Overflow { .. } => format!("overflow {} {}", pc, instruction),
+    }
+}
+
+fn parse<T: std::str::FromStr>(word: Option<&str>) -> io::Result<T> {
+    let word = word.ok_or_else(|| invalid("missing value".to_owned()))?;
+    word.parse()
+        .map_err(|_| invalid(format!("invalid value {:?}", word)))
+}
+
+fn parse_status<W: Word>(s: &str) -> io::Result<ProcessStatus<W>> {
+    let mut words = s.split_whitespace();
+    let status = match words.next() {
+        Some("paused") => Paused,
+        Some("outputting") => Outputting(parse(words.next())?),
+        Some("awaiting") => Awaiting(Parameter::Pos(parse(words.next())?)),
+        Some("exit") => Exit,
+        Some("exhausted") => Exhausted,
+        Some("looping") => Looping(parse(words.next())?),
+        Some("faulted") => {
+            let kind = words.next();
+            let pc = parse(words.next())?;
+            let instruction = parse(words.next())?;
+            Faulted(match kind {
+                Some("unknown-opcode") => UnknownOpcode { pc, instruction },
+                Some("unknown-mode") => UnknownMode {
+                    pc,
+                    instruction,
+                    mode: parse(words.next())?,
+                },
+                Some("write-to-immediate") => WriteToImmediate { pc, instruction },
+                Some("negative-address") => NegativeAddress {
+                    pc,
+                    instruction,
+                    address: parse(words.next())?,
+                },
+                Some("out-of-bounds") => OutOfBounds {
+                    pc,
+                    instruction,
+                    address: parse(words.next())?,
+                    limit: parse(words.next())?,
+                },
+                Some("not-awaiting") => NotAwaiting { pc, instruction },
+                Some("overflow") => Overflow { pc, instruction },
+                _ => Err(invalid(format!("unknown fault {:?}", s)))?,
+            })
+        }
+        _ => Err(invalid(format!("unknown status {:?}", s)))?,
+    };
+    Ok(status)
+}
+
+impl<W: Word> Process<W> {
+    pub fn write_snapshot(&self, mut out: impl Write) -> io::Result<()> {
+        writeln!(out, "{}", HEADER)?;
+        writeln!(out, "pc {}", self.pc)?;
+        writeln!(out, "rb {}", self.rb)?;
+        writeln!(out, "status {}", status_to_string(&self.status))?;
+        if let Some(budget) = self.budget {
+            writeln!(out, "budget {}", budget)?;
+        }
+        let join = |xs: &mut dyn Iterator<Item = &W>| {
+            xs.map(|x| x.to_string()).collect::<Vec<_>>().join(",")
+        };
+        if !self.inputs.is_empty() {
+            writeln!(out, "inputs {}", join(&mut self.inputs.iter()))?;
+        }
+        if !self.output.is_empty() {
+            writeln!(out, "output {}", join(&mut self.output.iter()))?;
+        }
+        let kind = if self.memory.is_sparse() {
+            "sparse"
+        } else {
+            "dense"
+        };
+        writeln!(out, "memory {} {}", kind, self.memory.limit())?;
+
+        let mut run: Option<(usize, Vec<String>)> = None;
+        for (a, x) in self.memory.cells() {
+            match &mut run {
+                Some((start, xs)) if *start + xs.len() == a => xs.push(x.to_string()),
+                _ => {
+                    if let Some((start, xs)) = run.take() {
+                        writeln!(out, "data {} {}", start, xs.join(","))?;
+                    }
+                    run = Some((a, vec![x.to_string()]));
+                }
+            }
+        }
+        if let Some((start, xs)) = run {
+            writeln!(out, "data {} {}", start, xs.join(","))?;
+        }
+        Ok(())
+    }
+
+    pub fn read_snapshot(input: impl BufRead) -> io::Result<Self> {
+        let mut lines = input.lines();
+        match lines.next() {
+            Some(Ok(ref h)) if h == HEADER => {}
+            _ => Err(invalid("not an intcode snapshot".to_owned()))?,
+        }
+        let mut process = Process::new(super::Intcode(vec![]));
+        for line in lines {
+            let line = line?;
+            let (key, value) = match line.find(' ') {
+                Some(i) => (&line[..i], &line[i + 1..]),
+                None => (line.as_str(), ""),
+            };
+            match key {
+                "pc" => process.pc = parse(Some(value))?,
+                "rb" => process.rb = parse(Some(value))?,
+                "status" => process.status = parse_status(value)?,
+                "budget" => process.budget = Some(parse(Some(value))?),
+                "inputs" => {
+                    for x in value.split(',') {
+                        process.inputs.push_back(parse(Some(x))?);
+                    }
+                }
+                "output" => {
+                    for x in value.split(',') {
+                        process.output.push(parse(Some(x))?);
+                    }
+                }
+                "memory" => {
+                    let mut words = value.split_whitespace();
+                    let memory = match words.next() {
+                        Some("dense") => Memory::dense(vec![]),
+                        Some("sparse") => Memory::sparse(vec![]),
+                        _ => Err(invalid(format!("unknown memory {:?}", value)))?,
+                    };
+                    process.memory = memory.with_limit(parse(words.next())?);
+                }
+                "data" => {
+                    let mut words = value.split_whitespace();
+                    let start: usize = parse(words.next())?;
+                    let cells = words.next().unwrap_or("").split(',');
+                    for (i, x) in cells.enumerate() {
+                        process
+                            .memory
+                            .set(start + i, parse(Some(x))?)
+                            .map_err(|limit| invalid(format!("data exceeds limit {}", limit)))?;
+                    }
+                }
+                "" => {}
+                _ => Err(invalid(format!("unknown field {:?}", key)))?,
+            }
+        }
+        Ok(process)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut out = BufWriter::new(File::create(path)?);
+        self.write_snapshot(&mut out)?;
+        out.flush()
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        Process::read_snapshot(BufReader::new(File::open(path)?))
+    }
+}
+
+#[test]
+pub fn snapshot_test() {
+    use super::Intcode;
+    let code: Intcode = "109,7,203,0,204,0,99".parse().unwrap();
+    let mut p = Process::sparse(code).with_memory_limit(1000);
+    assert_eq!(p.resume(), Awaiting(Parameter::Pos(7)));
+
+    let mut saved = vec![];
+    p.write_snapshot(&mut saved).unwrap();
+    let mut q: Process = Process::read_snapshot(saved.as_slice()).unwrap();
+    assert_eq!(q.pc(), 2);
+    assert_eq!(q.relative_base(), 7);
+    assert_eq!(q.status(), p.status());
+    assert_eq!(q.memory(), p.memory());
+
+    q.feed(5).unwrap();
+    assert_eq!(q.resume(), Outputting(5));
+    let mut out = vec![];
+    q.write_snapshot(&mut out).unwrap();
+    let text = String::from_utf8(out).unwrap();
+    assert!(
+        text.ends_with("status outputting 5\nmemory sparse 1000\ndata 0 109,7,203,0,204,0,99,5\n")
+    );
+}
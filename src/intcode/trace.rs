@@ -0,0 +1,209 @@
+//! Execution traces in JSON Lines, one object per executed instruction:
+//!
+//! ```text
+//! {"pc":2,"rb":0,"instruction":"ADD [225], [6] -> [6]","operands":[1,1100],"writes":[[6,1101]]}
+//! ```
+//!
+//! Inputs and outputs show up as `"input"` and `"output"` fields and faults
+//! as `"fault"`.
+
+use super::Opcode::*;
+use super::ProcessStatus::*;
+use super::{resume_by_steps, IntcodeError, Opcode, Parameter, Process, ProcessStatus, Word};
+use std::io::{self, Write};
+
+/// What a single instruction did.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Record<W> {
+    pub pc: usize,
+    pub rb: W,
+    pub op: Option<Opcode<Parameter<W>>>,
+    /// Values of the parameters the instruction reads.
+    pub operands: Vec<W>,
+    pub writes: Vec<(usize, W)>,
+    pub input: Option<W>,
+    pub output: Option<W>,
+    pub fault: Option<IntcodeError<W>>,
+}
+
+fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+impl<W: Word> Record<W> {
+    pub fn to_json(&self) -> String {
+        let list = |xs: Vec<String>| format!("[{}]", xs.join(","));
+        let mut fields = vec![format!("\"pc\":{}", self.pc), format!("\"rb\":{}", self.rb)];
+        if let Some(op) = &self.op {
+            fields.push(format!("\"instruction\":{}", quote(&op.to_string())));
+        }
+        let operands = self.operands.iter().map(|x| x.to_string()).collect();
+        fields.push(format!("\"operands\":{}", list(operands)));
+        let writes = self
+            .writes
+            .iter()
+            .map(|(a, x)| format!("[{},{}]", a, x))
+            .collect();
+        fields.push(format!("\"writes\":{}", list(writes)));
+        if let Some(x) = self.input {
+            fields.push(format!("\"input\":{}", x));
+        }
+        if let Some(x) = self.output {
+            fields.push(format!("\"output\":{}", x));
+        }
+        if let Some(e) = self.fault {
+            fields.push(format!("\"fault\":{}", quote(&e.to_string())));
+        }
+        format!("{{{}}}", fields.join(","))
+    }
+}
+
+/// A process that writes a record to `sink` for every instruction it runs.
+///
+/// `resume` and `feed` behave as on `Process`. An instruction the budget or
+/// the loop detector stops from running isn't recorded. If writing to the
+/// sink fails tracing stops, and the error is returned by `finish`.
+pub struct Tracer<W, S> {
+    process: Process<W>,
+    sink: S,
+    error: Option<io::Error>,
+}
+
+impl<W: Word, S: Write> Tracer<W, S> {
+    pub fn new(process: Process<W>, sink: S) -> Self {
+        Tracer {
+            process,
+            sink,
+            error: None,
+        }
+    }
+
+    pub fn process(&self) -> &Process<W> {
+        &self.process
+    }
+
+    fn emit(&mut self, record: Record<W>) {
+        if self.error.is_none() {
+            if let Err(e) = writeln!(self.sink, "{}", record.to_json()) {
+                self.error = Some(e);
+            }
+        }
+    }
+
+    fn record(&self) -> Record<W> {
+        Record {
+            pc: self.process.pc(),
+            rb: self.process.relative_base(),
+            op: self.process.current().ok(),
+            operands: vec![],
+            writes: vec![],
+            input: None,
+            output: None,
+            fault: None,
+        }
+    }
+
+    /// Executes one instruction and records it.
+    pub fn step(&mut self) -> ProcessStatus<W> {
+        let mut record = self.record();
+        let p = &self.process;
+        let (reads, dest) = match &record.op {
+            Some(Add(a, b, c)) | Some(Mul(a, b, c)) | Some(Lt(a, b, c)) | Some(Equ(a, b, c)) => {
+                (vec![a, b], p.address(c).ok())
+            }
+            Some(Jnz(a, b)) | Some(Jz(a, b)) => (vec![a, b], None),
+            Some(Out(a)) | Some(Arb(a)) => (vec![a], None),
+            Some(Inp(a)) => (vec![], p.address(a).ok()),
+            _ => (vec![], None),
+        };
+        record.operands = reads.into_iter().filter_map(|x| p.get(x).ok()).collect();
+
+        let status = self.process.step();
+        match status {
+            // recorded once the input is fed
+            Awaiting(_) => return status,
+            // stopped before the instruction ran
+            Exhausted | Looping(_) => return status,
+            Outputting(x) => record.output = Some(x),
+            Faulted(e) => record.fault = Some(e),
+            _ => {
+                if let Some(a) = dest {
+                    let x = self.process.memory().get(a);
+                    record.writes.push((a, x));
+                    if let Some(Inp(_)) = record.op {
+                        // taken from the input queue
+                        record.input = Some(x);
+                    }
+                }
+            }
+        }
+        self.emit(record);
+        status
+    }
+
+    pub fn resume(&mut self) -> ProcessStatus<W> {
+        resume_by_steps(self.process.status(), || self.step())
+    }
+
+    pub fn feed(&mut self, input: W) -> Result<ProcessStatus<W>, IntcodeError<W>> {
+        let mut record = self.record();
+        let dest = match self.process.status() {
+            Awaiting(Parameter::Pos(dest)) => Some(dest),
+            _ => None,
+        };
+        let status = self.process.feed(input)?;
+        record.input = Some(input);
+        record.writes.extend(dest.map(|a| (a, input)));
+        self.emit(record);
+        Ok(status)
+    }
+
+    /// Returns the process and sink, or the first error writing to the sink.
+    pub fn finish(mut self) -> io::Result<(Process<W>, S)> {
+        match self.error {
+            Some(e) => Err(e),
+            None => {
+                self.sink.flush()?;
+                Ok((self.process, self.sink))
+            }
+        }
+    }
+}
+
+#[test]
+pub fn trace_test() {
+    use super::Intcode;
+    let code: Intcode = "3,9,1002,9,2,9,4,9,99,0".parse().unwrap();
+    let mut t = Tracer::new(Process::new(code), vec![]);
+    t.resume();
+    t.feed(21).unwrap();
+    assert_eq!(t.resume(), Outputting(42));
+    assert_eq!(t.resume(), Exit);
+    let (_, sink) = t.finish().unwrap();
+    let lines: Vec<String> = String::from_utf8(sink)
+        .unwrap()
+        .lines()
+        .map(String::from)
+        .collect();
+    assert_eq!(
+        lines,
+        vec![
+            r#"{"pc":0,"rb":0,"instruction":"INP -> [9]","operands":[],"writes":[[9,21]],"input":21}"#,
+            r#"{"pc":2,"rb":0,"instruction":"MUL [9], #2 -> [9]","operands":[21,2],"writes":[[9,42]]}"#,
+            r#"{"pc":6,"rb":0,"instruction":"OUT [9]","operands":[42],"writes":[],"output":42}"#,
+            r#"{"pc":8,"rb":0,"instruction":"HLT","operands":[],"writes":[]}"#,
+        ]
+    );
+
+    // neither the instruction out of budget nor the jump closing the loop run
+    let code: Intcode = "1101,1,1,9,1105,1,4,99,0,0".parse().unwrap();
+    let p = Process::new(code.clone()).with_budget(1);
+    let mut t = Tracer::new(p, vec![]);
+    assert_eq!(t.resume(), Exhausted);
+    let (_, sink) = t.finish().unwrap();
+    assert_eq!(String::from_utf8(sink).unwrap().lines().count(), 1);
+    let mut t = Tracer::new(Process::new(code).with_loop_detection(), vec![]);
+    assert_eq!(t.resume(), Looping(4));
+    let (_, sink) = t.finish().unwrap();
+    assert_eq!(String::from_utf8(sink).unwrap().lines().count(), 2);
+}
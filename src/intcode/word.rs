@@ -0,0 +1,46 @@
+use std::convert::{TryFrom, TryInto};
+use std::fmt::{Debug, Display};
+use std::hash::Hash;
+use std::ops::{Add, Mul};
+use std::str::FromStr;
+
+/// Integer type stored in each memory cell of an Intcode program.
+pub trait Word:
+    Copy
+    + Debug
+    + Display
+    + Eq
+    + Ord
+    + Hash
+    + FromStr
+    + Add<Output = Self>
+    + Mul<Output = Self>
+    + From<i32>
+    + TryInto<i32>
+    + TryInto<usize>
+    + TryFrom<usize>
+{
+    /// `self + rhs`, or `None` if it overflows.
+    fn checked_add(self, rhs: Self) -> Option<Self>;
+
+    /// `self * rhs`, or `None` if it overflows.
+    fn checked_mul(self, rhs: Self) -> Option<Self>;
+}
+
+macro_rules! impl_word {
+    ($($t:ty),*) => {
+        $(impl Word for $t {
+            #[inline]
+            fn checked_add(self, rhs: Self) -> Option<Self> {
+                <$t>::checked_add(self, rhs)
+            }
+
+            #[inline]
+            fn checked_mul(self, rhs: Self) -> Option<Self> {
+                <$t>::checked_mul(self, rhs)
+            }
+        })*
+    };
+}
+
+impl_word!(i32, i64, i128);
@@ -1,11 +1,166 @@
-mod intcode;
-extern crate itertools;
-#[macro_use]
-mod solutions;
+use aoc19::intcode::ascii::AsciiProcess;
+use aoc19::intcode::cfg::Cfg;
+use aoc19::intcode::debugger::Debugger;
+use aoc19::intcode::device::Numbers;
+use aoc19::intcode::fuzz;
+use aoc19::intcode::profile::Profiler;
+use aoc19::intcode::trace::Tracer;
+use aoc19::intcode::{asm, disasm, Intcode, Process, ProcessStatus};
+use aoc19::solutions;
+use aoc19::solutions::day8::*;
 
-use solutions::day8::*;
+use std::env;
+use std::fmt::Display;
+use std::fs;
+use std::io;
+use std::process::exit;
+use std::str::FromStr;
 
-fn main() {
+fn load(path: &str) -> Intcode {
+    let parsed = fs::read_to_string(path)
+        .map_err(|e| e.to_string())
+        .and_then(|s| s.parse());
+    match parsed {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("Can't load {}: {}", path, e);
+            exit(1)
+        }
+    }
+}
+
+fn assemble(path: &str) {
+    let assembled = fs::read_to_string(path)
+        .map_err(|e| e.to_string())
+        .and_then(|s| asm::assemble::<i64>(&s));
+    match assembled {
+        Ok(Intcode(code)) => {
+            let words: Vec<String> = code.iter().map(|x| x.to_string()).collect();
+            println!("{}", words.join(","));
+        }
+        Err(e) => {
+            eprintln!("Can't assemble {}: {}", path, e);
+            exit(1)
+        }
+    }
+}
+
+/// Prints a JSON Lines trace of a run, feeding `inputs` in order.
+fn trace(path: &str, inputs: &[&str]) {
+    let stdout = io::stdout();
+    let mut tracer = Tracer::new(Process::new(load(path)), stdout.lock());
+    let mut inputs = inputs.iter();
+    loop {
+        match tracer.resume() {
+            ProcessStatus::Outputting(_) => continue,
+            ProcessStatus::Awaiting(_) => match inputs.next().map(|x| number(x)) {
+                Some(x) => {
+                    tracer.feed(x).unwrap();
+                }
+                None => break,
+            },
+            _ => break,
+        }
+    }
+    if let Err(e) = tracer.finish() {
+        eprintln!("Can't write trace: {}", e);
+        exit(1)
+    }
+}
+
+/// Runs a program feeding `inputs` in order, then prints its outputs and
+/// where it spent its time.
+fn profile(path: &str, inputs: &[&str]) {
+    let mut profiler = Profiler::new(Process::new(load(path)));
+    let mut inputs = inputs.iter();
+    loop {
+        match profiler.resume() {
+            ProcessStatus::Outputting(x) => println!("{}", x),
+            ProcessStatus::Awaiting(_) => match inputs.next().map(|x| number(x)) {
+                Some(x) => {
+                    profiler.feed(x).unwrap();
+                }
+                None => break,
+            },
+            _ => break,
+        }
+    }
+    print!("{}", profiler.profile());
+}
+
+/// Exits on an error reading stdin or writing stdout, quietly if the reader
+/// went away, e.g. `aoc19 run program | head -1`.
+fn io_error(e: io::Error) -> ! {
+    if e.kind() == io::ErrorKind::BrokenPipe {
+        exit(0)
+    }
+    eprintln!("I/O error: {}", e);
+    exit(1)
+}
+
+/// Runs an interactive debugging session on stdin and stdout.
+fn debug(path: &str) {
+    let mut debugger = Debugger::new(Process::new(load(path)));
+    let stdin = io::stdin();
+    if let Err(e) = debugger.repl(stdin.lock(), io::stdout()) {
+        io_error(e)
+    }
+}
+
+/// Runs a program on stdin and stdout, as text if `ascii` or else one number
+/// per line.
+fn run(path: &str, ascii: bool) {
+    let mut process = Process::new(load(path));
+    let status = if ascii {
+        let stdin = io::stdin();
+        AsciiProcess::with_process(process).interactive(stdin.lock(), io::stdout())
+    } else {
+        let (mut input, mut output) = (Numbers::stdin(), Numbers::stdout());
+        loop {
+            match process.run_with(&mut input, &mut output) {
+                // a typo at the console shouldn't end the session
+                Err(e) if e.kind() == io::ErrorKind::InvalidData => eprintln!("{}", e),
+                status => break status,
+            }
+        }
+    };
+    let status = status.unwrap_or_else(|e| io_error(e));
+    match status {
+        ProcessStatus::Exit => (),
+        ProcessStatus::Awaiting(_) => eprintln!("Out of input"),
+        ProcessStatus::Faulted(e) => {
+            eprintln!("{}", e);
+            exit(1)
+        }
+        s => {
+            eprintln!("Stopped: {:?}", s);
+            exit(1)
+        }
+    }
+}
+
+fn number<T: FromStr>(arg: &str) -> T
+where
+    T::Err: Display,
+{
+    arg.parse().unwrap_or_else(|e| {
+        eprintln!("Invalid number {:?}: {}", arg, e);
+        exit(1)
+    })
+}
+
+/// Compares the interpreter with the reference on random programs.
+fn fuzz(cases: usize, seed: u64) {
+    match fuzz::fuzz(seed, cases) {
+        None => println!("no divergence in {} programs", cases),
+        Some(divergence) => {
+            print!("{}", divergence);
+            exit(1)
+        }
+    }
+}
+
+fn day8() {
     let input: String = solutions::get_input(8).unwrap();
     //let input = "0222112222120000";
     let v: Option<Vec<u8>> = input.chars().map(digit_to_u8).collect();
@@ -14,3 +169,26 @@ fn main() {
     let flatimg = img.flatten();
     println!("{}", flatimg);
 }
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    match args.as_slice() {
+        [] => day8(),
+        ["disasm", path] => print!("{}", disasm::listing(&load(path))),
+        ["asm", path] => assemble(path),
+        ["cfg", path] => print!("{}", Cfg::new(&load(path)).to_dot()),
+        ["debug", path] => debug(path),
+        ["trace", path, inputs @ ..] => trace(path, inputs),
+        ["profile", path, inputs @ ..] => profile(path, inputs),
+        ["fuzz"] => fuzz(10000, 1),
+        ["fuzz", cases] => fuzz(number(cases), 1),
+        ["fuzz", cases, seed] => fuzz(number(cases), number(seed)),
+        ["run", path] | ["run", path, "--numeric"] => run(path, false),
+        ["run", path, "--ascii"] => run(path, true),
+        _ => {
+            eprintln!("usage: aoc19 [disasm <file> | asm <file> | cfg <file> | debug <file> | trace <file> [input...] | profile <file> [input...] | run <file> [--ascii | --numeric] | fuzz [cases [seed]]]");
+            exit(1)
+        }
+    }
+}
@@ -1,21 +1,21 @@
 use crate::intcode::{Intcode, Process, ProcessStatus};
 
-pub fn part1(input: &Intcode) -> i32 {
+pub fn part1(input: &Intcode) -> i64 {
     let mut p = Process::new(input.replace(12, 2));
     match p.resume() {
         ProcessStatus::Exit => p.head(),
         _ => panic!("Process is still running"),
     }
 }
-fn pairs<'a>(input: &'a Vec<i32>) -> Vec<(&'a i32, &'a i32)> {
+fn pairs<'a>(input: &'a Vec<i64>) -> Vec<(&'a i64, &'a i64)> {
     input
         .iter()
         .flat_map(|x| input.iter().map(move |y| (x, y)))
         .collect()
 }
 
-pub fn part2(input: &Intcode) -> i32 {
-    let possibilities: Vec<i32> = (1..=99).collect();
+pub fn part2(input: &Intcode) -> i64 {
+    let possibilities: Vec<i64> = (1..=99).collect();
     let ps = pairs(&possibilities);
     let (&n, &v) = ps
         .iter()
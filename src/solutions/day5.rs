@@ -1,27 +1,19 @@
-use crate::intcode::{Intcode, Process, ProcessStatus};
+use crate::intcode::{Intcode, Process};
 
-pub fn part1(input: &Intcode) -> i32 {
-    let mut p = Process::new(input.clone());
-    p.resume(); // start process
-    p.feed(1); // give command
-
-    let mut last = 0;
-    while let ProcessStatus::Outputting(x) = p.resume() {
-        last = x;
-    }
-    last
+pub fn part1(input: &Intcode) -> i64 {
+    Process::new(input.clone())
+        .with_inputs([1]) // give command
+        .outputs()
+        .last()
+        .unwrap()
 }
 
-pub fn part2(input: &Intcode) -> i32 {
-    let mut p = Process::new(input.clone());
-
-    p.resume(); // start process
-    p.feed(5); // give command
-    if let ProcessStatus::Outputting(x) = p.resume() {
-        x
-    } else {
-        panic!("Not outputing")
-    }
+pub fn part2(input: &Intcode) -> i64 {
+    Process::new(input.clone())
+        .with_inputs([5]) // give command
+        .outputs()
+        .next()
+        .unwrap()
 }
 
 #[test]
@@ -1,6 +1,6 @@
 use itertools::Itertools;
 
-use crate::intcode::{Intcode, Process, ProcessStatus};
+use crate::intcode::{Intcode, Process, ProcessStatus, ProcessStatus::*, Runnable};
 use std::iter::repeat;
 
 #[derive(Debug, Clone)]
@@ -27,77 +27,79 @@ impl IntoIterator for ProcessChain {
 }
 
 impl ProcessChain {
-    pub fn init(&mut self, phases: &Vec<i32>) {
-        let mut it = phases.iter();
-        for p in self.0.iter_mut() {
-            p.resume();
-            let n = it.next().unwrap();
-            p.feed(*n);
+    pub fn init(&mut self, phases: &Vec<i64>) {
+        for (p, n) in self.0.iter_mut().zip(phases) {
+            p.push_input(*n);
         }
     }
+}
 
-    pub fn feed(&mut self, value: i32) -> i32 {
-        let mut last = value;
-        for p in self.0.iter_mut() {
-            match p.resume() {
-                ProcessStatus::Awaiting(_) => p.feed(last),
-                x => panic!(format!("1: Unexpected status {:?}", x)),
-            };
-            match p.resume() {
-                ProcessStatus::Outputting(x) => last = x,
-                x => panic!(format!("2: Unexpected status {:?}", x)),
-            }
-        }
-        last
+impl Runnable for ProcessChain {
+    fn run(&mut self, inputs: &[i64]) -> Vec<i64> {
+        self.0
+            .iter_mut()
+            .fold(inputs.to_vec(), |signals, p| p.run(&signals))
+    }
+
+    /// The first amplifier that stopped abnormally, otherwise the first one
+    /// still running.
+    fn status(&self) -> ProcessStatus {
+        let statuses = || self.0.iter().map(|p| p.status());
+        statuses()
+            .find(|s| matches!(s, Faulted(_) | Exhausted | Looping(_)))
+            .or_else(|| statuses().find(|s| *s != Exit))
+            .unwrap_or(Exit)
     }
+}
+
+/// Passes `value` through the machine once.
+pub fn feed<R: Runnable>(machine: &mut R, value: i64) -> i64 {
+    *machine
+        .run(&[value])
+        .last()
+        .expect("amplifier gave no output")
+}
 
-    pub fn do_loop(&mut self, initial: i32) -> i32 {
-        let mut memory: Vec<i32> = vec![initial; self.0.len()];
-        while self.0.iter().any(|s| s.status() != ProcessStatus::Exit) {
-            //println!("{:?}", memory);
-            let mut i = 0;
-            for p in self.0.iter_mut() {
-                if let ProcessStatus::Awaiting(_) = p.resume() {
-                    let idx = (i + memory.len() - 1) % memory.len();
-                    let prev = memory[idx];
-                    p.feed(prev);
-                }
-                if let ProcessStatus::Outputting(x) = p.resume() {
-                    memory[i] = x;
-                }
-                if p.status() == ProcessStatus::Exit {}
-                i += 1;
-            }
+/// Feeds the machine its own output until it stops asking for input.
+pub fn feedback<R: Runnable>(machine: &mut R, initial: i64) -> i64 {
+    let mut signals = vec![initial];
+    let mut last = initial;
+    loop {
+        signals = machine.run(&signals);
+        if let Some(&x) = signals.last() {
+            last = x;
+        }
+        if !machine.awaiting() {
+            return last;
         }
-        memory[memory.len() - 1]
     }
 }
 
-pub fn part1(input: &Intcode) -> i32 {
+pub fn part1(input: &Intcode) -> i64 {
     let p = Process::new(input.clone());
     let chain: ProcessChain = repeat(p).take(5).collect();
-    let perm: Vec<Vec<i32>> = (0..=4).permutations(5).collect();
+    let perm: Vec<Vec<i64>> = (0..=4).permutations(5).collect();
     perm.iter()
         .map(|p| {
             let mut chain: ProcessChain = chain.clone();
             // initialize
             chain.init(p);
-            chain.feed(0)
+            feed(&mut chain, 0)
         })
         .max()
         .unwrap()
 }
 
-pub fn part2(input: &Intcode) -> i32 {
+pub fn part2(input: &Intcode) -> i64 {
     let p = Process::new(input.clone());
     let chain: ProcessChain = repeat(p).take(5).collect();
-    let perm: Vec<Vec<i32>> = (5..=9).permutations(5).collect();
+    let perm: Vec<Vec<i64>> = (5..=9).permutations(5).collect();
     perm.iter()
         .map(|p| {
             let mut chain: ProcessChain = chain.clone();
             // initialize
             chain.init(p);
-            chain.do_loop(0)
+            feedback(&mut chain, 0)
         })
         .max()
         .unwrap()
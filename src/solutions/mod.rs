@@ -47,15 +47,15 @@ impl Error for DayParseError {
 }
 
 #[allow(dead_code)]
-pub fn run_day(day: i32, part: i32) -> Result<i32, Box<dyn Error>> {
+pub fn run_day(day: i32, part: i32) -> Result<i64, Box<dyn Error>> {
     let default = DayParseError {
         day: day,
         part: part,
     };
     let res = match day {
         1 => match part {
-            1 => day1::part1(&get_input(1)?),
-            2 => day1::part2(&get_input(1)?),
+            1 => day1::part1(&get_input(1)?).into(),
+            2 => day1::part2(&get_input(1)?).into(),
             _ => Err(default)?,
         },
         2 => match part {
@@ -64,13 +64,13 @@ pub fn run_day(day: i32, part: i32) -> Result<i32, Box<dyn Error>> {
             _ => Err(default)?,
         },
         3 => match part {
-            1 => day3::part1(&get_input(3)?),
-            2 => day3::part2(&get_input(3)?),
+            1 => day3::part1(&get_input(3)?).into(),
+            2 => day3::part2(&get_input(3)?).into(),
             _ => Err(default)?,
         },
         4 => match part {
-            1 => day4::part1(&get_input(4)?),
-            2 => day4::part2(&get_input(4)?),
+            1 => day4::part1(&get_input(4)?).into(),
+            2 => day4::part2(&get_input(4)?).into(),
             _ => Err(default)?,
         },
         5 => match part {
@@ -79,8 +79,8 @@ pub fn run_day(day: i32, part: i32) -> Result<i32, Box<dyn Error>> {
             _ => Err(default)?,
         },
         6 => match part {
-            1 => day6::part1(&get_input(6)?),
-            2 => day6::part2(&get_input(6)?),
+            1 => day6::part1(&get_input(6)?).into(),
+            2 => day6::part2(&get_input(6)?).into(),
             _ => Err(default)?,
         },
         7 => match part {
@@ -89,8 +89,8 @@ pub fn run_day(day: i32, part: i32) -> Result<i32, Box<dyn Error>> {
             _ => Err(default)?,
         },
         8 => match part {
-            1 => day8::part1(&get_input(8)?),
-            //2 => day8::part2(&get_input(7)?),
+            1 => day8::part1(&get_input(8)?).into(),
+            //2 => day8::part2(&get_input(7)?).into(),
             _ => Err(default)?,
         },
         _ => Err(default)?,